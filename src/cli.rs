@@ -1,34 +1,124 @@
+use std::{collections::HashSet, io, path::PathBuf};
+
 use anyhow::Result;
-use clap::{ArgGroup, Parser, Subcommand};
+use chrono::Duration;
+use clap::{ArgGroup, CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
+use colour::green_ln;
 
 use crate::{
-    handlers::{config::JSONConfigHandler, task::NotionAPITaskHandler},
-    models::task_status::TaskStatus,
-    services::{config::JSONConfigService, notion::NotionAPI},
-    traits::{ConfigCommandHandler, ConfigService, TaskHandler},
+    completions::{self, CompletionKind},
+    handlers::{
+        config::JSONConfigHandler,
+        doctor::NotionDoctorHandler,
+        init::{Connect, NotionInitHandler},
+        sync::NotionSyncHandler,
+        task::{ConnectDatabase, NotionAPITaskHandler},
+    },
+    manual,
+    models::{
+        list_output::ListOutput, property_mapping::PropertyMapping, task_filter::parse_age,
+        task_format::TaskFormat, task_selector::TaskSelector, task_status::TaskStatus,
+    },
+    services::{
+        cache::JSONCacheService, cached_notion::CachedNotion, config::JSONConfigService,
+        journal::JSONJournalService, listing::JSONListingService, notion::NotionAPI,
+    },
+    traits::{
+        CacheService, ConfigCommandHandler, ConfigService, DoctorCommandHandler,
+        InitCommandHandler, NotionCaller, SyncCommandHandler, TaskHandler,
+    },
 };
 
+/// Where the Notion API is served from
+const NOTION_BASE_URL: &str = "https://api.notion.com";
+
 impl Cli {
     /// Routes the command to the correct handler
     pub fn route_command(&self) -> Result<()> {
         match &self.command {
-            Command::Tasks { subcommand } => {
+            Command::Tasks {
+                database,
+                subcommand,
+            } => {
                 let config = JSONConfigService::new().get_config()?;
-                let notion = NotionAPI::new("https://api.notion.com".to_string(), config.token)?;
-                let task_handler = NotionAPITaskHandler::new(Box::new(notion));
+                let task_handler = NotionAPITaskHandler::new(
+                    self.connect_database(config.token.clone()),
+                    Box::new(JSONListingService::new()),
+                    self.dry_run,
+                );
+                let database = database.as_deref();
 
                 match subcommand {
-                    TaskSubcommand::Add { name, status } => {
-                        task_handler.add(&config.database_id, name, status)?;
+                    TaskSubcommand::Add { name, status, body } => {
+                        task_handler.add(&config.find_database(database)?, name, status, body)?;
+                    }
+                    TaskSubcommand::List {
+                        status,
+                        with_id,
+                        output,
+                        all_databases,
+                    } => {
+                        let databases = if *all_databases {
+                            config.all_databases()?
+                        } else {
+                            config.find_databases(database)?
+                        };
+                        task_handler.list(&databases, status, with_id, *output)?;
+                    }
+                    TaskSubcommand::Done { selector } => {
+                        task_handler.done(&config.find_database(database)?, selector)?;
+                    }
+                    TaskSubcommand::Update {
+                        selector,
+                        to,
+                        title,
+                    } => {
+                        task_handler.update(
+                            &config.find_database(database)?,
+                            selector,
+                            to,
+                            title,
+                        )?;
+                    }
+                    TaskSubcommand::Show { selector } => {
+                        task_handler.show(&config.find_database(database)?, selector)?;
                     }
-                    TaskSubcommand::List { status, with_id } => {
-                        task_handler.list(&config.database_id, status, with_id)?;
+                    TaskSubcommand::Archive {
+                        selector,
+                        older_than,
+                        yes,
+                    } => {
+                        task_handler.archive(
+                            &config.find_database(database)?,
+                            selector,
+                            older_than,
+                            *yes,
+                        )?;
                     }
-                    TaskSubcommand::Done { ids, name } => {
-                        task_handler.done(&config.database_id, ids, name.as_deref())?;
+                    TaskSubcommand::Restore { id } => {
+                        task_handler.restore(&config.find_database(database)?, id)?;
                     }
-                    TaskSubcommand::Update { id, to, name } => {
-                        task_handler.update(id, to, name)?;
+                    TaskSubcommand::Comment { id, text } => {
+                        task_handler.comment(&config.find_database(database)?, id, text)?;
+                    }
+                    TaskSubcommand::Comments { id } => {
+                        task_handler.comments(&config.find_database(database)?, id)?;
+                    }
+                    TaskSubcommand::Import {
+                        file,
+                        format,
+                        id_column,
+                    } => {
+                        task_handler.import(
+                            &config.find_database(database)?,
+                            file,
+                            format,
+                            id_column,
+                        )?;
+                    }
+                    TaskSubcommand::Export { format, out } => {
+                        task_handler.export(&config.find_database(database)?, *format, out)?;
                     }
                 };
 
@@ -36,10 +126,21 @@ impl Cli {
             }
             Command::Config { subcommand } => {
                 let config_service = JSONConfigService::new();
+                let config = config_service.get_config()?;
                 let notion = NotionAPI::new(
-                    "https://api.notion.com".to_string(),
-                    config_service.get_config()?.token,
+                    NOTION_BASE_URL.to_string(),
+                    config.token,
+                    config.mapping,
+                    self.dry_run,
                 )?;
+                let notion = CachedNotion::new(
+                    Box::new(notion),
+                    Box::new(JSONCacheService::new()),
+                    Box::new(JSONJournalService::new()),
+                    self.offline,
+                    self.dry_run,
+                    None,
+                );
                 let handler = JSONConfigHandler::new(Box::new(notion), Box::new(config_service));
 
                 match subcommand {
@@ -50,8 +151,19 @@ impl Cli {
                         DatabaseConfigSubcommand::List => {
                             handler.list_databases()?;
                         }
-                        DatabaseConfigSubcommand::Set { database_id } => {
-                            handler.set_database(database_id)?;
+                        DatabaseConfigSubcommand::Set { database_id, alias } => {
+                            handler.set_database(database_id, alias)?;
+                        }
+                        DatabaseConfigSubcommand::Add { database_id, alias } => {
+                            handler.add_database(database_id, alias)?;
+                        }
+                        DatabaseConfigSubcommand::Alias { subcommand } => match subcommand {
+                            AliasConfigSubcommand::List => {
+                                handler.list_aliases()?;
+                            }
+                        },
+                        DatabaseConfigSubcommand::Create { parent, title } => {
+                            handler.create_database(parent, title)?;
                         }
                     },
                     ConfigSubcommand::Token { subcommand } => match subcommand {
@@ -61,10 +173,130 @@ impl Cli {
                     },
                 }
             }
+            Command::Init => {
+                let handler =
+                    NotionInitHandler::new(Box::new(JSONConfigService::new()), self.connect());
+
+                handler.init()?;
+            }
+            Command::Doctor => {
+                let handler =
+                    NotionDoctorHandler::new(Box::new(JSONConfigService::new()), self.connect());
+
+                handler.doctor()?;
+            }
+            Command::Man { out_dir } => match out_dir {
+                Some(dir) => {
+                    let written = manual::write_pages(Cli::command(), dir)?;
+                    green_ln!("Wrote {} man pages to {}", written, dir.display());
+                }
+                None => manual::write_root_page(Cli::command(), &mut io::stdout())?,
+            },
+            Command::Completions { shell } => {
+                completions::write_script(*shell, &mut Cli::command(), &mut io::stdout())?;
+            }
+            Command::Complete { kind } => {
+                // completion must stay quiet, so a missing config or cache just means no suggestions
+                let Result::Ok(config) = JSONConfigService::new().get_config() else {
+                    return Ok(());
+                };
+                let Result::Ok(cache) = JSONCacheService::new().get_cache() else {
+                    return Ok(());
+                };
+
+                let Result::Ok(databases) = config.all_databases() else {
+                    return Ok(());
+                };
+
+                let mut printed = HashSet::new();
+                for database in databases {
+                    let Some(db) = cache.databases.get(&database.id) else {
+                        continue;
+                    };
+                    for candidate in completions::candidates(*kind, db, &database.mapping) {
+                        if printed.insert(candidate.clone()) {
+                            println!("{}", candidate);
+                        }
+                    }
+                }
+            }
+            Command::Sync {
+                force,
+                full,
+                database,
+            } => {
+                let config = JSONConfigService::new().get_config()?;
+                let databases = match database {
+                    Some(names) => config.find_databases(Some(names))?,
+                    None => config.all_databases()?,
+                };
+                let handler = NotionSyncHandler::new(
+                    self.connect_api(config.token.clone()),
+                    config.all_databases()?,
+                    Box::new(JSONJournalService::new()),
+                    Box::new(JSONCacheService::new()),
+                    self.dry_run,
+                );
+
+                handler.sync(&databases, *force, *full)?;
+            }
         };
 
         return Ok(());
     }
+
+    /// Creates cached Notion clients for the databases task commands use, each with its own property mapping
+    fn connect_database(&self, token: String) -> ConnectDatabase {
+        let (dry_run, offline) = (self.dry_run, self.offline);
+
+        return Box::new(move |database| {
+            let notion = NotionAPI::new(
+                NOTION_BASE_URL.to_string(),
+                token.clone(),
+                database.mapping.clone(),
+                dry_run,
+            )?;
+            let notion = CachedNotion::new(
+                Box::new(notion),
+                Box::new(JSONCacheService::new()),
+                Box::new(JSONJournalService::new()),
+                offline,
+                dry_run,
+                Some(database.id.clone()),
+            );
+            return Ok(Box::new(notion) as Box<dyn NotionCaller>);
+        });
+    }
+
+    /// Creates uncached Notion clients for the databases sync talks to, each with its own property mapping
+    fn connect_api(&self, token: String) -> ConnectDatabase {
+        let dry_run = self.dry_run;
+
+        return Box::new(move |database| {
+            let notion = NotionAPI::new(
+                NOTION_BASE_URL.to_string(),
+                token.clone(),
+                database.mapping.clone(),
+                dry_run,
+            )?;
+            return Ok(Box::new(notion) as Box<dyn NotionCaller>);
+        });
+    }
+
+    /// Creates Notion clients for handlers that only learn the token as they run
+    fn connect(&self) -> Connect {
+        let dry_run = self.dry_run;
+
+        return Box::new(move |token| {
+            let notion = NotionAPI::new(
+                NOTION_BASE_URL.to_string(),
+                token.to_string(),
+                PropertyMapping::default(),
+                dry_run,
+            )?;
+            return Ok(Box::new(notion) as Box<dyn NotionCaller>);
+        });
+    }
 }
 
 // clap structs below
@@ -80,6 +312,12 @@ pub struct Cli {
     /// Subcommands
     #[clap(subcommand)]
     command: Command,
+    /// Print the requests that would change Notion instead of sending them
+    #[clap(long, global = true)]
+    dry_run: bool,
+    /// Read tasks from the local cache and queue changes instead of talking to Notion
+    #[clap(long, global = true)]
+    offline: bool,
 }
 
 /// Defines the different subcommands that can be called
@@ -87,22 +325,75 @@ pub struct Cli {
 enum Command {
     /// Performs operations with tasks
     Tasks {
+        /// The database to use instead of the default one, by alias, title, ID or Notion URL, see `notion config database get`
+        #[clap(long, short, global = true)]
+        database: Option<String>,
         /// Task operation to perform
         #[clap(subcommand)]
         subcommand: TaskSubcommand,
     },
+    /// Guided setup, asks for a token and picks the task database
+    #[clap(after_help = "EXAMPLES:\n    Set up the CLI for the first time:\n        notion init")]
+    Init,
+    /// Checks the config, token and database and suggests fixes for problems
+    #[clap(after_help = "EXAMPLES:\n    Find out why task commands fail:\n        notion doctor")]
+    Doctor,
     /// Used to configure the database task commands interact with
     Config {
         /// Config operation to perform
         #[clap(subcommand)]
         subcommand: ConfigSubcommand,
     },
+    /// Print a shell completion script
+    #[clap(
+        after_help = "EXAMPLES:\n    Install bash completions:\n        notion completions bash > ~/.local/share/bash-completion/completions/notion"
+    )]
+    Completions {
+        /// The shell to print the script for
+        #[clap(value_enum)]
+        shell: Shell,
+    },
+    /// Print completion candidates from the cache, used by the completion scripts
+    #[clap(name = "complete-hook", hide = true)]
+    Complete {
+        /// What to complete
+        #[clap(value_enum)]
+        kind: CompletionKind,
+    },
+    /// Write man pages for every command
+    #[clap(
+        after_help = "EXAMPLES:\n    Read the manual without installing it:\n        notion man | man -l -\n    Install the man pages:\n        notion man --out-dir ~/.local/share/man/man1"
+    )]
+    Man {
+        /// Write a page for every subcommand into this directory instead of printing the main page
+        #[clap(long, short)]
+        out_dir: Option<PathBuf>,
+    },
+    /// Sends changes queued while Notion couldn't be reached and pulls tasks edited since the last sync
+    #[clap(
+        after_help = "EXAMPLES:\n    Send queued changes and pull changed tasks:\n        notion sync\n    Refetch every task:\n        notion sync --full\n    Send queued changes and only pull the tasks of one database:\n        notion sync --database work"
+    )]
+    Sync {
+        /// Apply changes to tasks that were edited in Notion after the change was queued
+        #[clap(long)]
+        force: bool,
+        /// Refetch every task instead of only the ones edited since the last sync
+        #[clap(long)]
+        full: bool,
+        /// Only pull these databases, a comma separated list of aliases, titles, IDs or URLs
+        /// Queued changes are sent for every database
+        #[clap(long, short)]
+        database: Option<String>,
+    },
 }
 
 /// Defines the task commands that can be performed
 #[derive(Subcommand)]
 enum TaskSubcommand {
     /// Lists the tasks in the database
+    #[clap(
+        after_help = "EXAMPLES:\n    List all tasks and their IDs:\n        notion tasks list --with-id\n    List all in-progress tasks:\n        notion tasks list --status doing\n    Mark every in-progress task as done:\n        notion tasks list --status doing --output ids | notion tasks done -\n    List the tasks of two databases together:\n        notion tasks list --database personal,work\n    List the tasks of every database in the config:\n        notion tasks list --all-databases"
+    )]
     List {
         /// The status of the tasks to list
         #[clap(long, short, value_enum)]
@@ -110,38 +401,136 @@ enum TaskSubcommand {
         /// Whether or not to include the task ID in the listing
         #[clap(long, short)]
         with_id: bool,
+        /// How to print the tasks, ids prints one task ID per line for piping into other commands
+        #[clap(long, short, value_enum, default_value_t)]
+        output: ListOutput,
+        /// List the tasks of every database in the config, --database also takes a comma separated list
+        #[clap(long, conflicts_with = "database")]
+        all_databases: bool,
     },
     /// Add a task to the database
+    #[clap(
+        after_help = "EXAMPLES:\n    Add a new todo task to the board:\n        notion tasks add \"Adopt five cats\" --status todo\n    Add a task with page content written in markdown:\n        notion tasks add \"Adopt five cats\" --status todo --body \"Pick **names** and buy _plenty_ of litter\"\n    Add a task for every line of a file:\n        cat todo.txt | notion tasks add - --status todo\n    Add a task to another database in the config:\n        notion tasks add \"Write the quarterly report\" --status todo --database work"
+    )]
     Add {
-        /// The name of the task
+        /// The name of the task, or - to add a task for every line of stdin
         #[clap(required = true)]
         name: String,
         /// Status of the task to add
         #[clap(long, short, value_enum)]
         status: TaskStatus,
+        /// Page content for the task, written in markdown
+        #[clap(long, short)]
+        body: Option<String>,
     },
-    /// Update a task
-    #[clap(group(ArgGroup::new("update").required(true).multiple(true).args(&["to", "name"])))]
-    Update {
-        /// The ID of the task to update
+    /// Show tasks and their page content
+    #[clap(
+        after_help = "EXAMPLES:\n    Show the third task of the last listing:\n        notion tasks show 3\n    Show a task by ID prefix:\n        notion tasks show 59ad"
+    )]
+    Show {
+        /// The tasks to show
+        #[clap(flatten)]
+        selector: TaskSelector,
+    },
+    /// Archive tasks, optionally only the ones that haven't been edited for a while
+    #[clap(
+        after_help = "EXAMPLES:\n    Archive a task by ID:\n        notion tasks archive 59ad73ec-efbd-4b37-ad53-02c8f3b17c56\n    Archive every done task that hasn't been edited in 30 days:\n        notion tasks archive --status done --older-than 30d\n    Archive old unfinished tasks matching a filter:\n        notion tasks archive --filter \"status!=done and age>90d\""
+    )]
+    Archive {
+        /// The tasks to archive
+        #[clap(flatten)]
+        selector: TaskSelector,
+        /// Archive tasks that haven't been edited for this long, e.g. 30d, 2w or 12h
+        #[clap(long, value_parser = parse_age, group = "selector")]
+        older_than: Option<Duration>,
+        /// Archive without asking for confirmation
+        #[clap(long, short)]
+        yes: bool,
+    },
+    /// Restore an archived task
+    #[clap(
+        after_help = "EXAMPLES:\n    Restore an archived task:\n        notion tasks restore 59ad73ec-efbd-4b37-ad53-02c8f3b17c56"
+    )]
+    Restore {
+        /// The full ID of the task to restore, as printed by `tasks archive`, or its number from the last listing
+        /// ID prefixes can't be used, Notion doesn't list archived tasks
+        #[clap(required = true)]
+        id: String,
+    },
+    /// Comment on a task
+    #[clap(
+        after_help = "EXAMPLES:\n    Comment on a task:\n        notion tasks comment 59ad \"Three down, two to go\""
+    )]
+    Comment {
+        /// The ID, ID prefix or number from the last listing of the task to comment on
+        #[clap(required = true)]
+        id: String,
+        /// The comment text
+        #[clap(required = true)]
+        text: String,
+    },
+    /// List the comments on a task
+    #[clap(
+        after_help = "EXAMPLES:\n    List the comments on a task with their author and timestamp:\n        notion tasks comments 59ad"
+    )]
+    Comments {
+        /// The ID, ID prefix or number from the last listing of the task
         #[clap(required = true)]
         id: String,
+    },
+    /// Update tasks
+    #[clap(
+        after_help = "EXAMPLES:\n    Move a task to todo and update its title:\n        notion tasks update 59ad --to todo --name \"Finish civ6 game\"\n    Move every task matching \"civ\" back to doing:\n        notion tasks update --match civ --all --to doing"
+    )]
+    #[clap(group(ArgGroup::new("update").required(true).multiple(true).args(&["to", "title"])))]
+    // --name has always set the new title here, so selecting by name uses --match instead
+    #[clap(mut_arg("name", |a| a.long("match").short('m')))]
+    Update {
+        /// The tasks to update
+        #[clap(flatten)]
+        selector: TaskSelector,
         /// Status to mark the tasks as
         #[clap(long, short, value_enum)]
         to: Option<TaskStatus>,
-        /// New name for the task (only considered if single task specified)
-        #[clap(long, short)]
-        name: Option<String>,
+        /// New name for the task (only allowed if a single task is selected)
+        #[clap(long = "name", short = 'n', alias = "title")]
+        title: Option<String>,
     },
     /// Mark tasks as done
-    #[clap(group(ArgGroup::new("done").required(true).multiple(false).args(&["ids", "name"])))]
+    #[clap(
+        after_help = "EXAMPLES:\n    Mark a task as done by name:\n        notion tasks done --name \"Boil ocean\"\n    Mark every task matching a name as done:\n        notion tasks done --name ocean --all\n    Mark tasks matching a filter as done:\n        notion tasks done --filter \"status=doing and title~cat\""
+    )]
     Done {
-        /// A list of task IDs to mark as done
-        ids: Vec<String>,
-        /// The name of a task to mark as done
-        /// Doesn't have to be an exist match, this will match to a task if the task title contains the given string (case insensitive)
+        /// The tasks to mark as done
+        #[clap(flatten)]
+        selector: TaskSelector,
+    },
+    /// Create tasks from a csv, json, markdown checklist or todo.txt file
+    #[clap(
+        after_help = "EXAMPLES:\n    Preview the tasks a csv file would create:\n        notion tasks import backlog.csv --dry-run"
+    )]
+    Import {
+        /// The file to import
+        #[clap(required = true)]
+        file: PathBuf,
+        /// The file's format, guessed from its extension if not given
+        #[clap(long, short, value_enum)]
+        format: Option<TaskFormat>,
+        /// Column holding an ID from the source tool, rows whose ID was already imported are skipped
+        #[clap(long, default_value = "id")]
+        id_column: String,
+    },
+    /// Export every task with all its properties
+    #[clap(
+        after_help = "EXAMPLES:\n    Export every task to a csv file:\n        notion tasks export --format csv --out tasks.csv"
+    )]
+    Export {
+        /// The format to write
+        #[clap(long, short, value_enum)]
+        format: TaskFormat,
+        /// File to write to instead of stdout
         #[clap(long, short)]
-        name: Option<String>,
+        out: Option<PathBuf>,
     },
 }
 
@@ -150,24 +539,83 @@ enum TaskSubcommand {
 enum ConfigSubcommand {
     /// Database configuration subcommand
     Database {
+        /// Database config operation to perform
         #[clap(subcommand)]
         subcommand: DatabaseConfigSubcommand,
     },
     /// Token configuration subcommand
     Token {
+        /// Token config operation to perform
         #[clap(subcommand)]
         subcommand: TokenConfigSubcommand,
     },
 }
 
+/// Defines the database config commands that can be performed
 #[derive(Subcommand)]
 enum DatabaseConfigSubcommand {
+    /// Print the ID of the database task commands use and the other databases in the config
     Get,
-    Set { database_id: String },
+    /// Set the database task commands use
+    #[clap(
+        after_help = "EXAMPLES:\n    Use a database listed by `notion config database list`:\n        notion config database set 59ad73ec-efbd-4b37-ad53-02c8f3b17c56\n    Use the database a Notion link points to:\n        notion config database set \"https://www.notion.so/Tasks-59ad73ecefbd4b37ad5302c8f3b17c56?v=8c1f\"\n    Use a database and name it work:\n        notion config database set 59ad73ec-efbd-4b37-ad53-02c8f3b17c56 --alias work"
+    )]
+    Set {
+        /// ID of the database, as printed by `notion config database list`, or its Notion URL
+        database_id: String,
+        /// A short name to pick the database with in --database
+        #[clap(long)]
+        alias: Option<String>,
+    },
+    /// Add another database task commands can use with --database, keeping the default one
+    #[clap(
+        after_help = "EXAMPLES:\n    Add a work database next to the default one:\n        notion config database add 8f21c0de-4b5a-4968-8776-a5b4c3d2e1f0 --alias work\n    List its tasks:\n        notion tasks list --database work"
+    )]
+    Add {
+        /// ID of the database, as printed by `notion config database list`, or its Notion URL
+        database_id: String,
+        /// A short name to pick the database with in --database
+        #[clap(long)]
+        alias: Option<String>,
+    },
+    /// Database alias subcommand
+    Alias {
+        /// Alias operation to perform
+        #[clap(subcommand)]
+        subcommand: AliasConfigSubcommand,
+    },
+    /// List the databases shared with the integration that have a title and a status with todo, doing and done options
+    List,
+    /// Create a task database under a page and use it, the page has to be shared with the integration
+    #[clap(
+        after_help = "EXAMPLES:\n    Create a database called Tasks in a page shared with the integration:\n        notion config database create --parent 1c2e4a6b8d0f4e21a3b5c7d9e1f3a5b7 --title Tasks"
+    )]
+    Create {
+        /// ID or URL of the page to create the database in
+        #[clap(long, required = true)]
+        parent: String,
+        /// Title of the new database
+        #[clap(long, default_value = "Tasks")]
+        title: String,
+    },
+}
+
+/// Defines the database alias commands that can be performed
+#[derive(Subcommand)]
+enum AliasConfigSubcommand {
+    /// List the database aliases, set them with `notion config database set <ID> --alias <NAME>`
     List,
 }
 
+/// Defines the token config commands that can be performed
 #[derive(Subcommand)]
 enum TokenConfigSubcommand {
-    Set { token: String },
+    /// Set the integration token used to talk to Notion
+    #[clap(
+        after_help = "EXAMPLES:\n    Use the internal integration token from https://www.notion.so/my-integrations:\n        notion config token set secret_abc123"
+    )]
+    Set {
+        /// The internal integration token
+        token: String,
+    },
 }
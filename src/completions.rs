@@ -0,0 +1,154 @@
+use std::io::Write;
+
+use anyhow::Result;
+use clap::ValueEnum;
+use clap_complete::Shell;
+
+use crate::models::{
+    cache::DatabaseCache, property_mapping::PropertyMapping, task_status::TaskStatus,
+};
+
+/// What the hidden `complete-hook` command suggests
+#[derive(clap::ValueEnum, Clone, Debug, Copy, PartialEq, Eq)]
+pub enum CompletionKind {
+    /// Cached task IDs, each followed by a tab and the task title
+    Tasks,
+    /// Cached task titles
+    Titles,
+    /// Status values the cached schema has options for
+    Statuses,
+}
+
+/// Subcommands whose task arguments are completed from the cache
+const TASK_COMMANDS: &str = "done update show archive";
+
+/// Bash wrapper that answers task arguments from the cache and leaves the rest to the generated function
+const BASH_DYNAMIC: &str = r#"
+_notion_dynamic() {
+    local cur="${COMP_WORDS[COMP_CWORD]}" prev="${COMP_WORDS[COMP_CWORD-1]}"
+    if [[ "${COMP_WORDS[1]}" == tasks && " TASK_COMMANDS " == *" ${COMP_WORDS[2]} "* && ${COMP_CWORD} -gt 2 && "${cur}" != -* ]]; then
+        case "${prev}" in
+            --name|-n|--match|-m)
+                local IFS=$'\n'
+                COMPREPLY=($(compgen -W "$(notion complete-hook titles 2>/dev/null)" -- "${cur}"))
+                COMPREPLY=("${COMPREPLY[@]// /\\ }")
+                return 0
+                ;;
+            --status|-s|--to|-t)
+                COMPREPLY=($(compgen -W "$(notion complete-hook statuses 2>/dev/null)" -- "${cur}"))
+                return 0
+                ;;
+            --all|-a|${COMP_WORDS[2]}|[!-]*)
+                COMPREPLY=($(compgen -W "$(notion complete-hook tasks 2>/dev/null | cut -f1)" -- "${cur}"))
+                return 0
+                ;;
+        esac
+    fi
+    _notion "$@"
+}
+
+complete -F _notion_dynamic -o bashdefault -o default notion
+"#;
+
+/// Zsh helpers that the generated task ID and name specs are pointed at
+const ZSH_DYNAMIC: &str = r#"_notion_task_ids() {
+    local -a tasks
+    tasks=(${(f)"$(notion complete-hook tasks 2>/dev/null)"})
+    tasks=(${tasks//$'\t'/:})
+    _describe -t tasks 'task' tasks
+}
+
+_notion_task_titles() {
+    local -a titles
+    titles=(${(f)"$(notion complete-hook titles 2>/dev/null)"})
+    compadd -a titles
+}
+
+"#;
+
+/// Fish completions added on top of the generated ones
+const FISH_DYNAMIC: &str = r#"
+complete -c notion -n "__fish_seen_subcommand_from tasks; and __fish_seen_subcommand_from TASK_COMMANDS" -f -a "(notion complete-hook tasks 2>/dev/null)"
+complete -c notion -n "__fish_seen_subcommand_from tasks; and __fish_seen_subcommand_from TASK_COMMANDS" -s n -l name -r -f -a "(notion complete-hook titles 2>/dev/null)"
+complete -c notion -n "__fish_seen_subcommand_from tasks; and __fish_seen_subcommand_from TASK_COMMANDS" -s s -l status -r -f -a "(notion complete-hook statuses 2>/dev/null)"
+complete -c notion -n "__fish_seen_subcommand_from tasks; and __fish_seen_subcommand_from update" -s m -l match -r -f -a "(notion complete-hook titles 2>/dev/null)"
+complete -c notion -n "__fish_seen_subcommand_from tasks; and __fish_seen_subcommand_from update" -s t -l to -r -f -a "(notion complete-hook statuses 2>/dev/null)"
+"#;
+
+/// Write the completion script for a shell
+/// Bash, zsh and fish also complete task IDs, titles and statuses from the cache through `notion complete-hook`
+pub fn write_script(shell: Shell, cmd: &mut clap::Command, out: &mut dyn Write) -> Result<()> {
+    let mut generated = Vec::new();
+    clap_complete::generate(shell, cmd, "notion", &mut generated);
+    let mut script = String::from_utf8(generated)?;
+
+    match shell {
+        Shell::Bash => script.push_str(&BASH_DYNAMIC.replace("TASK_COMMANDS", TASK_COMMANDS)),
+        Shell::Fish => script.push_str(&FISH_DYNAMIC.replace("TASK_COMMANDS", TASK_COMMANDS)),
+        Shell::Zsh => {
+            // point the generated specs for task IDs and names at the helpers, which have to be defined before _notion runs
+            script = script
+                .lines()
+                .map(|line| match line {
+                    l if l.starts_with("'*::ids -- ") && l.ends_with(":' \\") => {
+                        l.replace(":' \\", ":_notion_task_ids' \\")
+                    }
+                    l if (l.starts_with("'--name=[") || l.starts_with("'--match=["))
+                        && l.ends_with(":NAME: ' \\") =>
+                    {
+                        l.replace(":NAME: ' \\", ":NAME:_notion_task_titles' \\")
+                    }
+                    l => l.to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            script.push('\n');
+
+            if let Some(call) = script.rfind("_notion \"$@\"") {
+                script.insert_str(call, ZSH_DYNAMIC);
+            }
+        }
+        _ => {}
+    }
+
+    out.write_all(script.as_bytes())?;
+
+    return Ok(());
+}
+
+/// Suggestions for the hidden `complete-hook` command, answered from the cache so completing never waits on Notion
+pub fn candidates(
+    kind: CompletionKind,
+    db: &DatabaseCache,
+    mapping: &PropertyMapping,
+) -> Vec<String> {
+    return match kind {
+        CompletionKind::Tasks => db
+            .tasks
+            .values()
+            .map(|t| format!("{}\t{}", t.id, t.title))
+            .collect(),
+        CompletionKind::Titles => db.tasks.values().map(|t| t.title.clone()).collect(),
+        CompletionKind::Statuses => {
+            let options = db
+                .schema
+                .as_ref()
+                .and_then(|s| s.get(&mapping.status))
+                .map(|p| p.option_names())
+                .unwrap_or_default();
+
+            let mut statuses: Vec<TaskStatus> = options
+                .into_iter()
+                .filter_map(|name| mapping.status_of(name))
+                .collect();
+            statuses.dedup();
+
+            // without a cached schema every status is offered
+            if statuses.is_empty() {
+                statuses = TaskStatus::value_variants().to_vec();
+            }
+
+            statuses.iter().map(|s| s.to_string()).collect()
+        }
+    };
+}
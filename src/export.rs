@@ -0,0 +1,245 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::Write,
+};
+
+use anyhow::Result;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+
+use crate::models::{task::Task, task_format::TaskFormat, task_status::TaskStatus};
+
+/// Columns every export starts with, the task's other properties follow in alphabetical order
+const FIXED_COLUMNS: [&str; 4] = ["id", "title", "status", "last_edited_time"];
+
+/// A task as it's written to JSON, fields keep this order
+#[derive(Serialize)]
+struct ExportedTask<'a> {
+    /// The task's ID
+    id: &'a str,
+    /// The task's title
+    title: &'a str,
+    /// The task's status
+    status: TaskStatus,
+    /// When the task was last edited
+    last_edited_time: DateTime<Utc>,
+    /// The task's other properties, sorted by name
+    properties: &'a BTreeMap<String, String>,
+}
+
+/// Write tasks in the given format
+pub fn write_tasks(tasks: &[Task], format: TaskFormat, out: &mut dyn Write) -> Result<()> {
+    return match format {
+        TaskFormat::Csv => write_csv(tasks, out),
+        TaskFormat::Json => write_json(tasks, out),
+        TaskFormat::Markdown => write_markdown(tasks, out),
+        TaskFormat::Todotxt => write_todotxt(tasks, out),
+    };
+}
+
+/// Format a timestamp the same way serde does in JSON exports
+fn timestamp(time: &DateTime<Utc>) -> String {
+    return time.to_rfc3339_opts(SecondsFormat::Secs, true);
+}
+
+/// Names of every property used by any of the tasks, sorted
+fn property_names(tasks: &[Task]) -> BTreeSet<&str> {
+    return tasks
+        .iter()
+        .flat_map(|t| t.properties.keys())
+        .map(String::as_str)
+        .collect();
+}
+
+/// One row per task, tasks without a property get an empty cell
+fn write_csv(tasks: &[Task], out: &mut dyn Write) -> Result<()> {
+    let properties = property_names(tasks);
+    let mut writer = csv::Writer::from_writer(out);
+
+    writer.write_record(FIXED_COLUMNS.iter().chain(properties.iter()))?;
+
+    for task in tasks {
+        let mut record = vec![
+            task.id.clone(),
+            task.title.clone(),
+            task.status.to_string(),
+            timestamp(&task.last_edited_time),
+        ];
+        for name in &properties {
+            record.push(task.properties.get(*name).cloned().unwrap_or_default());
+        }
+
+        writer.write_record(&record)?;
+    }
+
+    writer.flush()?;
+
+    return Ok(());
+}
+
+/// A pretty printed array of task objects
+fn write_json(tasks: &[Task], out: &mut dyn Write) -> Result<()> {
+    let exported: Vec<ExportedTask> = tasks
+        .iter()
+        .map(|t| ExportedTask {
+            id: &t.id,
+            title: &t.title,
+            status: t.status,
+            last_edited_time: t.last_edited_time,
+            properties: &t.properties,
+        })
+        .collect();
+
+    serde_json::to_writer_pretty(&mut *out, &exported)?;
+    writeln!(out)?;
+
+    return Ok(());
+}
+
+/// A checklist with a section per status, each task's details are nested under it
+fn write_markdown(tasks: &[Task], out: &mut dyn Write) -> Result<()> {
+    writeln!(out, "# Tasks")?;
+
+    for status in [TaskStatus::Todo, TaskStatus::Doing, TaskStatus::Done] {
+        let in_status: Vec<&Task> = tasks.iter().filter(|t| t.status == status).collect();
+        if in_status.is_empty() {
+            continue;
+        }
+
+        writeln!(out, "\n## {}\n", status)?;
+
+        let check = if status == TaskStatus::Done { "x" } else { " " };
+        for task in in_status {
+            writeln!(out, "- [{}] {}", check, task.title)?;
+            writeln!(out, "  - id: {}", task.id)?;
+            writeln!(
+                out,
+                "  - last_edited_time: {}",
+                timestamp(&task.last_edited_time)
+            )?;
+            for (name, value) in task.properties.iter().filter(|(_, v)| !v.is_empty()) {
+                writeln!(out, "  - {}: {}", name, value)?;
+            }
+        }
+    }
+
+    return Ok(());
+}
+
+/// One line per task, done tasks are completed with their last edit date
+/// Properties become key:value tags, spaces in keys become dashes and in values underscores
+fn write_todotxt(tasks: &[Task], out: &mut dyn Write) -> Result<()> {
+    for task in tasks {
+        let mut line = String::new();
+
+        if task.status == TaskStatus::Done {
+            line.push_str(&format!("x {} ", task.last_edited_time.format("%Y-%m-%d")));
+        }
+        line.push_str(&format!(
+            "{} status:{} id:{}",
+            task.title, task.status, task.id
+        ));
+
+        for (name, value) in task.properties.iter().filter(|(_, v)| !v.is_empty()) {
+            let key: String = name
+                .to_lowercase()
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join("-")
+                .replace(':', "");
+            let value = value.split_whitespace().collect::<Vec<_>>().join("_");
+            line.push_str(&format!(" {}:{}", key, value));
+        }
+
+        writeln!(out, "{}", line)?;
+    }
+
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    /// Tasks with uneven property sets, the second has properties the first lacks and the third none
+    fn tasks() -> Vec<Task> {
+        let edited = Utc.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap();
+        let task = |id: &str, properties: &[(&str, &str)]| {
+            return Task::new(
+                id.to_string(),
+                TaskStatus::Todo,
+                format!("Task {}", id),
+                edited,
+                false,
+                properties
+                    .iter()
+                    .map(|(n, v)| (n.to_string(), v.to_string()))
+                    .collect(),
+            );
+        };
+
+        return vec![
+            task("1", &[("Priority", "High")]),
+            task(
+                "2",
+                &[("Tags", "a, b"), ("Due", "2024-02-01"), ("Priority", "")],
+            ),
+            task("3", &[]),
+        ];
+    }
+
+    fn export(format: TaskFormat) -> String {
+        let mut out = Vec::new();
+        write_tasks(&tasks(), format, &mut out).unwrap();
+
+        return String::from_utf8(out).unwrap();
+    }
+
+    #[test]
+    fn csv_has_fixed_columns_then_sorted_properties() {
+        let csv = export(TaskFormat::Csv);
+        let lines: Vec<&str> = csv.lines().collect();
+
+        assert_eq!(
+            lines[0],
+            "id,title,status,last_edited_time,Due,Priority,Tags"
+        );
+        assert_eq!(lines[1], "1,Task 1,todo,2024-01-02T03:04:05Z,,High,");
+        assert_eq!(
+            lines[2],
+            "2,Task 2,todo,2024-01-02T03:04:05Z,2024-02-01,,\"a, b\""
+        );
+        assert_eq!(lines[3], "3,Task 3,todo,2024-01-02T03:04:05Z,,,");
+    }
+
+    #[test]
+    fn json_fields_keep_their_order() {
+        let json = export(TaskFormat::Json);
+        let tasks: Vec<serde_json::Value> = serde_json::from_str(&json).unwrap();
+
+        // the text is checked rather than the parsed value, whose maps don't keep the order
+        let fields: Vec<usize> = [
+            "\"id\"",
+            "\"title\"",
+            "\"status\"",
+            "\"last_edited_time\"",
+            "\"properties\"",
+        ]
+        .iter()
+        .map(|f| json.find(f).unwrap())
+        .collect();
+        assert!(fields.windows(2).all(|w| w[0] < w[1]), "{}", json);
+
+        let second = &json[json.find("\"2\"").unwrap()..json.find("\"3\"").unwrap()];
+        let properties: Vec<usize> = ["\"Due\"", "\"Priority\"", "\"Tags\""]
+            .iter()
+            .map(|p| second.find(p).unwrap())
+            .collect();
+        assert!(properties.windows(2).all(|w| w[0] < w[1]), "{}", second);
+
+        assert_eq!(tasks.len(), 3);
+        assert_eq!(tasks[2]["properties"], serde_json::json!({}));
+    }
+}
@@ -1,7 +1,14 @@
 use anyhow::{bail, Ok, Result};
-use colour::{green, red_ln};
+use colour::{green, green_ln, red, red_ln};
 
-use crate::traits::{ConfigCommandHandler, ConfigService, NotionCaller};
+use crate::{
+    models::{
+        config::DatabaseConfig,
+        database::{self, Database},
+        property_mapping::PropertyMapping,
+    },
+    traits::{ConfigCommandHandler, ConfigService, NotionCaller},
+};
 
 /// A ConfigHandler that persists the task database_id as json
 pub struct JSONConfigHandler {
@@ -15,14 +22,93 @@ impl JSONConfigHandler {
     pub fn new(notion: Box<dyn NotionCaller>, config: Box<dyn ConfigService>) -> JSONConfigHandler {
         return JSONConfigHandler { notion, config };
     }
+
+    /// Check that the database can be reached and hold tasks, keeping the mapping if the schema fits it
+    /// Returns the database as it will be saved in the config
+    fn check_database(
+        &self,
+        database_id: &str,
+        mapping: &PropertyMapping,
+        alias: &Option<String>,
+    ) -> Result<DatabaseConfig> {
+        let database_id = database::parse_id(database_id)?;
+
+        // --database splits on commas and tries IDs, so an alias must be neither
+        if let Some(alias) = alias {
+            if alias.trim().is_empty() || alias.contains(',') {
+                bail!("an alias can't be empty or contain commas, got '{}'", alias);
+            }
+            if database::parse_id(alias).is_ok() {
+                bail!(
+                    "'{}' looks like a database ID, pick a name for the alias",
+                    alias
+                );
+            }
+        }
+
+        let schema = match self.notion.get_database_schema(&database_id) {
+            Result::Ok(schema) => schema,
+            Err(e) => bail!(
+                "can't reach database {}, open it in Notion, click Share and invite the integration ({})",
+                database_id,
+                e
+            ),
+        };
+
+        let mut mapping = mapping.clone();
+        let problems = mapping.problems(&schema);
+        if !problems.is_empty() {
+            let Some(detected) = PropertyMapping::detect(&schema) else {
+                bail!(
+                    "database {} can't hold tasks: {}",
+                    database_id,
+                    problems.join(", ")
+                );
+            };
+
+            green_ln!("Detected properties, {}", detected);
+            mapping = detected;
+        }
+
+        let title = self.notion.get_database_title(&database_id)?;
+
+        return Ok(DatabaseConfig {
+            id: database_id,
+            title,
+            mapping,
+            alias: alias.as_ref().map(|a| a.trim().to_string()),
+        });
+    }
 }
 
 impl ConfigCommandHandler for JSONConfigHandler {
     // creates a config file ~/.notion-cli/config.json and populates it with the database_id to use
-    // maybe should be refactored eventually for testability and to optionally use a wizard to find the correct db
-    fn set_database(&self, database_id: &str) -> Result<()> {
+    // the database is checked first so a broken config never gets saved, `notion init` is the guided alternative
+    fn set_database(&self, database_id: &str, alias: &Option<String>) -> Result<()> {
         let mut config = self.config.get_config()?;
-        config.database_id = database_id.to_string();
+        let database = self.check_database(database_id, &config.mapping, alias)?;
+
+        config.database_id = database.id.clone();
+        config.mapping = database.mapping.clone();
+        config.add_database(database);
+        self.config.set_config(config)?;
+
+        return Ok(());
+    }
+
+    fn add_database(&self, database_id: &str, alias: &Option<String>) -> Result<()> {
+        let mut config = self.config.get_config()?;
+        let database = self.check_database(database_id, &config.mapping, alias)?;
+
+        green!("Added the {} database, use it with ", database.title);
+        red_ln!("--database \"{}\"", database.name());
+
+        // the first database added becomes the default
+        if config.database_id.is_empty() {
+            config.database_id = database.id.clone();
+            config.mapping = database.mapping.clone();
+        }
+        config.add_database(database);
         self.config.set_config(config)?;
 
         return Ok(());
@@ -32,11 +118,56 @@ impl ConfigCommandHandler for JSONConfigHandler {
         let config = self.config.get_config()?;
         if config.database_id.is_empty() {
             bail!("No database ID set");
-        } else {
-            green!("Database ID: ");
-            red_ln!("{}", config.database_id);
-            Ok(())
         }
+
+        green!("Database ID: ");
+        red_ln!("{}", config.database_id);
+
+        let others: Vec<&DatabaseConfig> = config
+            .databases
+            .iter()
+            .filter(|d| d.id != config.database_id)
+            .collect();
+        if !others.is_empty() {
+            red_ln!("Other databases ----------------------------------------------------------");
+            for (i, database) in others.into_iter().enumerate() {
+                Database::new(
+                    database.id.clone(),
+                    database.title.clone(),
+                    database.mapping.clone(),
+                )
+                .print(i);
+            }
+            red_ln!("------------------------------------------------------------------------");
+        }
+
+        return Ok(());
+    }
+
+    fn list_aliases(&self) -> Result<()> {
+        let config = self.config.get_config()?;
+        let aliased: Vec<&DatabaseConfig> = config
+            .databases
+            .iter()
+            .filter(|d| d.alias.is_some())
+            .collect();
+
+        if aliased.is_empty() {
+            red_ln!(
+                "No aliases set, add one with `notion config database set <ID> --alias <NAME>`"
+            );
+            return Ok(());
+        }
+
+        red_ln!("Database aliases -------------------------------------------------------");
+        for database in aliased {
+            green!("{}: ", database.name());
+            red!("{}", database.title);
+            green_ln!(" ({})", database.id);
+        }
+        red_ln!("------------------------------------------------------------------------");
+
+        return Ok(());
     }
 
     fn list_databases(&self) -> Result<()> {
@@ -56,6 +187,33 @@ impl ConfigCommandHandler for JSONConfigHandler {
         return Ok(());
     }
 
+    fn create_database(&self, parent_page_id: &str, title: &str) -> Result<()> {
+        let mapping = PropertyMapping::default();
+        let parent_page_id = database::parse_id(parent_page_id)?;
+        let Some(database) = self
+            .notion
+            .create_database(&parent_page_id, title, &mapping)?
+        else {
+            return Ok(());
+        };
+
+        green!("Created database {} with ID: ", database.title);
+        red_ln!("{}", database.id);
+
+        let mut config = self.config.get_config()?;
+        config.database_id = database.id.clone();
+        config.mapping = database.mapping.clone();
+        config.add_database(DatabaseConfig {
+            id: database.id,
+            title: database.title,
+            mapping: database.mapping,
+            alias: None,
+        });
+        self.config.set_config(config)?;
+
+        return Ok(());
+    }
+
     fn set_token(&self, token: &str) -> Result<()> {
         let mut config = self.config.get_config()?;
         config.token = token.to_string();
@@ -0,0 +1,273 @@
+use anyhow::{bail, Result};
+use colour::{green_ln, red_ln, yellow_ln};
+
+use crate::{
+    models::property_mapping::PropertyMapping,
+    services::{cached_notion::is_unreachable, notion::NOTION_VERSION},
+    traits::{ConfigService, DoctorCommandHandler},
+};
+
+use super::init::Connect;
+
+/// Checks the config and what Notion makes of it
+pub struct NotionDoctorHandler {
+    /// The config being checked
+    config: Box<dyn ConfigService>,
+    /// Creates a Notion client for the configured token
+    connect: Connect,
+}
+
+impl NotionDoctorHandler {
+    /// Create a new NotionDoctorHandler
+    pub fn new(config: Box<dyn ConfigService>, connect: Connect) -> NotionDoctorHandler {
+        return NotionDoctorHandler { config, connect };
+    }
+
+    /// Run the checks in order, stopping at the first one the rest depend on
+    /// Returns the number of failed checks
+    fn run_checks(&self) -> usize {
+        let mut failed = 0;
+        let mut fail = |problem: String, fix: &str| {
+            red_ln!("✗ {}", problem);
+            yellow_ln!("  fix: {}", fix);
+            failed += 1;
+        };
+
+        green_ln!("✓ Using Notion-Version {}", NOTION_VERSION);
+
+        match self.config.exists() {
+            Ok(true) => {}
+            Ok(false) => {
+                fail(
+                    "No config file at ~/.notion-cli/config.json".to_string(),
+                    "run `notion init`",
+                );
+                return failed;
+            }
+            Err(e) => {
+                fail(
+                    format!("The config file can't be found: {}", e),
+                    "set the HOME environment variable",
+                );
+                return failed;
+            }
+        }
+
+        let config = match self.config.get_config() {
+            Ok(config) => {
+                green_ln!("✓ Config file found");
+                config
+            }
+            Err(e) => {
+                fail(
+                    format!("The config file can't be read: {}", e),
+                    "fix ~/.notion-cli/config.json by hand or run `notion init` to write a new one",
+                );
+                return failed;
+            }
+        };
+
+        if config.token.is_empty() {
+            fail(
+                "No token set".to_string(),
+                "run `notion config token set <TOKEN>` with the token from https://www.notion.so/my-integrations",
+            );
+            return failed;
+        }
+
+        let notion = match (self.connect)(&config.token) {
+            Ok(notion) => notion,
+            Err(e) => {
+                fail(
+                    format!("Can't create a Notion client: {}", e),
+                    "run `notion init`",
+                );
+                return failed;
+            }
+        };
+
+        match notion.get_current_user() {
+            Ok(name) => {
+                green_ln!("✓ Token is valid, connected as {}", name);
+            }
+            Err(e) if is_unreachable(&e) => {
+                fail(
+                    format!("Notion can't be reached: {}", e),
+                    "check your internet connection, task commands still work with --offline",
+                );
+                return failed;
+            }
+            Err(e) => {
+                fail(
+                    format!("Notion rejected the token: {}", e),
+                    "copy the token again from https://www.notion.so/my-integrations and run `notion config token set <TOKEN>`",
+                );
+                return failed;
+            }
+        }
+
+        if config.database_id.is_empty() {
+            fail(
+                "No database set".to_string(),
+                "run `notion config database list` and then `notion config database set <ID>`",
+            );
+            return failed;
+        }
+
+        let databases = match config.all_databases() {
+            Ok(databases) => databases,
+            Err(e) => {
+                fail(
+                    format!("The databases can't be listed: {}", e),
+                    "run `notion config database set <ID>`",
+                );
+                return failed;
+            }
+        };
+
+        for database in databases {
+            let is_default = database.id == config.database_id;
+            let name = format!("{} ({})", database.name(), database.id);
+
+            let schema = match notion.get_database_schema(&database.id) {
+                Ok(schema) => {
+                    green_ln!("✓ Database {} is reachable", name);
+                    schema
+                }
+                Err(e) => {
+                    let fix = if is_default {
+                        "open the database in Notion, click Share and invite the integration, or pick another one with `notion config database set <ID>`"
+                    } else {
+                        "open the database in Notion, click Share and invite the integration"
+                    };
+                    fail(format!("Database {} can't be reached: {}", name, e), fix);
+                    continue;
+                }
+            };
+
+            let problems = database.mapping.problems(&schema);
+            if problems.is_empty() {
+                green_ln!("✓ Database {} schema matches {}", name, database.mapping);
+                continue;
+            }
+
+            // the default database's mapping lives at the top of the config, which init rewrites
+            let fix = match PropertyMapping::detect(&schema) {
+                Some(mapping) if is_default => format!("run `notion init` to use {}", mapping),
+                Some(mapping) => format!(
+                    "run `notion config database add {}` to use {}",
+                    database.id, mapping
+                ),
+                None => format!(
+                    "add the property or option in Notion, tasks need {}",
+                    database.mapping
+                ),
+            };
+            for problem in problems {
+                fail(format!("Database {}: {}", name, problem), &fix);
+            }
+        }
+
+        return failed;
+    }
+}
+
+impl DoctorCommandHandler for NotionDoctorHandler {
+    fn doctor(&self) -> Result<()> {
+        let failed = self.run_checks();
+        if failed > 0 {
+            bail!("{} check(s) failed", failed);
+        }
+
+        green_ln!("Everything looks good");
+
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::{
+        models::{
+            api::schema::Schema,
+            config::{AppConfig, DatabaseConfig},
+        },
+        services::fake::{FakeNotion, MemoryConfig},
+        traits::NotionCaller,
+    };
+
+    use super::*;
+
+    /// A schema that fits the default mapping
+    fn default_schema() -> Schema {
+        return serde_json::from_value(json!({
+            "Name": { "type": "title", "title": {} },
+            "Status": {
+                "type": "select",
+                "select": { "options": [{ "name": "To Do" }, { "name": "Doing" }, { "name": "Done🙌" }] },
+            },
+        }))
+        .unwrap();
+    }
+
+    /// A doctor for a config with a default database db1 and a second database db2
+    fn doctor(notion: &FakeNotion) -> NotionDoctorHandler {
+        let database = |id: &str, title: &str| DatabaseConfig {
+            id: id.to_string(),
+            title: title.to_string(),
+            mapping: PropertyMapping::default(),
+            alias: None,
+        };
+        let config = MemoryConfig::with_config(AppConfig {
+            database_id: "db1".to_string(),
+            token: "secret".to_string(),
+            mapping: PropertyMapping::default(),
+            databases: vec![database("db1", "Personal"), database("db2", "Work")],
+        });
+
+        let notion = notion.clone();
+        return NotionDoctorHandler::new(
+            Box::new(config),
+            Box::new(move |_| Ok(Box::new(notion.clone()) as Box<dyn NotionCaller>)),
+        );
+    }
+
+    #[test]
+    fn passes_when_every_database_fits() {
+        let notion = FakeNotion::default();
+        notion.0.borrow_mut().schemas = [("db1", default_schema()), ("db2", default_schema())]
+            .into_iter()
+            .map(|(id, schema)| (id.to_string(), schema))
+            .collect();
+
+        assert_eq!(doctor(&notion).run_checks(), 0);
+    }
+
+    #[test]
+    fn checks_databases_after_the_default_one() {
+        let notion = FakeNotion::default();
+        notion
+            .0
+            .borrow_mut()
+            .schemas
+            .insert("db1".to_string(), default_schema());
+
+        // db2 has no properties, so both its title and its status are missing
+        assert_eq!(doctor(&notion).run_checks(), 2);
+    }
+
+    #[test]
+    fn keeps_checking_after_an_unreachable_database() {
+        let notion = FakeNotion::default();
+        notion.0.borrow_mut().failing.push("db1".to_string());
+        notion
+            .0
+            .borrow_mut()
+            .schemas
+            .insert("db2".to_string(), default_schema());
+
+        assert_eq!(doctor(&notion).run_checks(), 1);
+    }
+}
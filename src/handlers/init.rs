@@ -0,0 +1,100 @@
+use anyhow::{bail, Result};
+use colour::{green_ln, red_ln};
+
+use crate::{
+    models::config::{AppConfig, DatabaseConfig},
+    prompt,
+    traits::{ConfigService, InitCommandHandler, NotionCaller},
+};
+
+/// Builds a Notion client for a token, the wizard only knows the token once it has been typed
+pub type Connect = Box<dyn Fn(&str) -> Result<Box<dyn NotionCaller>>>;
+
+/// A setup wizard that writes the config through a ConfigService
+pub struct NotionInitHandler {
+    /// Where the config is saved
+    config: Box<dyn ConfigService>,
+    /// Creates a Notion client once the token is known
+    connect: Connect,
+}
+
+impl NotionInitHandler {
+    /// Create a new NotionInitHandler
+    pub fn new(config: Box<dyn ConfigService>, connect: Connect) -> NotionInitHandler {
+        return NotionInitHandler { config, connect };
+    }
+
+    /// Ask for a token until Notion accepts one, returns the token and a client using it
+    fn ask_token(&self, current: &str) -> Result<(String, Box<dyn NotionCaller>)> {
+        let question = if current.is_empty() {
+            "Integration token (from https://www.notion.so/my-integrations)"
+        } else {
+            "Integration token (leave empty to keep the current one)"
+        };
+
+        loop {
+            let mut token = prompt::secret(question)?;
+            if token.is_empty() {
+                if current.is_empty() {
+                    continue;
+                }
+                token = current.to_string();
+            }
+
+            let notion = (self.connect)(&token)?;
+            match notion.get_current_user() {
+                Ok(name) => {
+                    green_ln!("Connected as {}", name);
+                    return Ok((token, notion));
+                }
+                Err(e) => {
+                    red_ln!("Notion rejected the token: {}", e);
+                }
+            }
+        }
+    }
+}
+
+impl InitCommandHandler for NotionInitHandler {
+    fn init(&self) -> Result<()> {
+        let current = self.config.get_config()?;
+        let (token, notion) = self.ask_token(&current.token)?;
+
+        let mut databases = notion.list_eligible_databases()?;
+        if databases.is_empty() {
+            bail!("no task databases are shared with this integration, open the database in Notion, click Share and invite the integration, then run `notion init` again");
+        }
+
+        let choice = if databases.len() == 1 {
+            0
+        } else {
+            let titles: Vec<String> = databases
+                .iter()
+                .map(|d| format!("{} ({})", d.title, d.id))
+                .collect();
+            prompt::pick("Which database holds your tasks?", &titles)?
+        };
+        let database = databases.swap_remove(choice);
+        green_ln!("Using the {} database", database.title);
+
+        green_ln!("Detected properties, {}", database.mapping);
+
+        let mut config = AppConfig {
+            database_id: database.id.clone(),
+            token,
+            mapping: database.mapping.clone(),
+            databases: current.databases,
+        };
+        config.add_database(DatabaseConfig {
+            id: database.id,
+            title: database.title,
+            mapping: database.mapping,
+            alias: None,
+        });
+        self.config.set_config(config)?;
+
+        green_ln!("Config saved, try `notion tasks list`");
+
+        return Ok(());
+    }
+}
@@ -1,4 +1,10 @@
 /// Module for handling the config command
 pub mod config;
+/// Module for handling the doctor command
+pub mod doctor;
+/// Module for handling the init command
+pub mod init;
+/// Module for handling the sync command
+pub mod sync;
 /// Module for handling the tasks command
 pub mod task;
@@ -0,0 +1,471 @@
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet, VecDeque},
+    rc::Rc,
+};
+
+use anyhow::Result;
+use colour::{green_ln, red_ln, yellow_ln};
+
+use crate::{
+    handlers::task::ConnectDatabase,
+    models::{
+        config::DatabaseConfig,
+        journal::{Journal, JournalEntry, Operation},
+    },
+    services::sync,
+    traits::{CacheService, JournalService, NotionCaller, SyncCommandHandler},
+};
+
+/// A sync handler that replays the journal against the Notion API
+pub struct NotionSyncHandler {
+    /// Builds the Notion client of a database, changes are sent straight to it
+    connect: ConnectDatabase,
+    /// Every database in the config, the default one first
+    databases: Vec<DatabaseConfig>,
+    /// The clients built so far, by database ID
+    clients: RefCell<HashMap<String, Rc<dyn NotionCaller>>>,
+    /// The queued changes
+    journal: Box<dyn JournalService>,
+    /// The local copy that changed tasks are pulled into
+    cache: Box<dyn CacheService>,
+    /// Whether the client only prints changes, the journal is then left as it is
+    dry_run: bool,
+}
+
+impl NotionSyncHandler {
+    /// Create a new NotionSyncHandler
+    /// databases holds every database in the config with the default one first, changes queued
+    /// without a database are replayed in it
+    pub fn new(
+        connect: ConnectDatabase,
+        databases: Vec<DatabaseConfig>,
+        journal: Box<dyn JournalService>,
+        cache: Box<dyn CacheService>,
+        dry_run: bool,
+    ) -> NotionSyncHandler {
+        return NotionSyncHandler {
+            connect,
+            databases,
+            clients: RefCell::new(HashMap::new()),
+            journal,
+            cache,
+            dry_run,
+        };
+    }
+
+    /// The Notion client of a database, built the first time it is needed
+    fn client(&self, database: &DatabaseConfig) -> Result<Rc<dyn NotionCaller>> {
+        if let Some(client) = self.clients.borrow().get(&database.id) {
+            return Ok(client.clone());
+        }
+
+        let client: Rc<dyn NotionCaller> = Rc::from((self.connect)(database)?);
+        self.clients
+            .borrow_mut()
+            .insert(database.id.clone(), client.clone());
+
+        return Ok(client);
+    }
+
+    /// The client for the database a change was made in, with that database's property mapping
+    /// Changes queued without a database, or in one that's no longer in the config, use the default database
+    fn client_for(&self, entry: &JournalEntry) -> Result<Rc<dyn NotionCaller>> {
+        let database_id = match &entry.operation {
+            Operation::AddTask { database_id, .. } => Some(database_id),
+            _ => entry.database_id.as_ref(),
+        };
+        let database = database_id
+            .and_then(|id| self.databases.iter().find(|d| d.id == *id))
+            .unwrap_or(&self.databases[0]);
+
+        return self.client(database);
+    }
+
+    /// Send a queued change to Notion
+    fn apply(&self, notion: &dyn NotionCaller, operation: &Operation) -> Result<()> {
+        return match operation {
+            Operation::AddTask {
+                database_id,
+                title,
+                status,
+                body,
+                properties,
+            } => notion.add_task(database_id, title, status, body, properties),
+            Operation::MarkAsDone { id } => notion.mark_as_done(std::slice::from_ref(id)),
+            Operation::UpdateTask { id, to, name } => notion.update_task(id, to, name),
+            Operation::ArchiveTask { id } => notion.archive_task(id),
+            Operation::RestoreTask { id } => notion.restore_task(id),
+            Operation::AddComment { id, text } => notion.add_comment(id, text),
+        };
+    }
+
+    /// Whether the page was edited in Notion after the change was queued
+    fn has_conflict(&self, notion: &dyn NotionCaller, entry: &JournalEntry) -> Result<bool> {
+        let (Some(id), Some(base)) = (entry.operation.task_id(), entry.base_edited_time) else {
+            return Ok(false);
+        };
+
+        let remote = notion.get_task(id)?;
+
+        return Ok(remote.last_edited_time > base);
+    }
+
+    /// Pull the tasks edited in Notion into the cache, labelled with the database when several are pulled
+    fn pull(&self, database: &DatabaseConfig, full: bool, labelled: bool) -> Result<()> {
+        let notion = self.client(database)?;
+        let mut cache = self.cache.get_cache()?;
+        let db = cache.databases.entry(database.id.clone()).or_default();
+
+        let summary = sync::pull(notion.as_ref(), &database.id, db, full)?;
+        if db.schema.is_none() || summary.full {
+            db.schema = Some(notion.get_database_schema(&database.id)?);
+        }
+        self.cache.set_cache(&cache)?;
+
+        let label = match labelled {
+            true => format!("{}: ", database.name()),
+            false => String::new(),
+        };
+        if summary.full {
+            green_ln!(
+                "{}Pulled all {} task(s), removed {} stale task(s)",
+                label,
+                summary.updated,
+                summary.removed
+            );
+        } else {
+            green_ln!(
+                "{}Pulled {} changed task(s), removed {} task(s) that are no longer tasks",
+                label,
+                summary.updated,
+                summary.removed
+            );
+        }
+
+        return Ok(());
+    }
+
+    /// Replay the journal, entries that can't be applied stay queued
+    /// Once a change to a task stays queued, the later changes to that task stay queued behind it
+    fn push(&self, force: bool) -> Result<()> {
+        let journal = self.journal.get_journal()?;
+        if journal.entries.is_empty() {
+            green_ln!("No queued changes");
+            return Ok(());
+        }
+
+        let total = journal.entries.len();
+        let mut pending: VecDeque<JournalEntry> = journal.entries.into();
+        let mut kept: Vec<JournalEntry> = Vec::new();
+        let mut blocked: HashSet<String> = HashSet::new();
+
+        while let Some(entry) = pending.pop_front() {
+            let description = entry.operation.describe();
+            let task_id = entry.operation.task_id().map(str::to_string);
+
+            // an entry that stays queued is an error, None when the reason was already printed
+            let result = match &task_id {
+                Some(id) if blocked.contains(id) => {
+                    yellow_ln!(
+                        "Skipped: {}, an earlier change to the task is still queued",
+                        description
+                    );
+                    kept.push(entry);
+                    self.save(&kept, &pending)?;
+                    continue;
+                }
+                _ => match self
+                    .client_for(&entry)
+                    .and_then(|notion| Ok((self.has_conflict(notion.as_ref(), &entry)?, notion)))
+                {
+                    Ok((true, _)) if !force => {
+                        yellow_ln!(
+                            "Conflict: {} was edited in Notion after it was queued, pass --force to apply it anyway",
+                            description
+                        );
+                        Err(None)
+                    }
+                    Ok((_, notion)) => self
+                        .apply(notion.as_ref(), &entry.operation)
+                        .map_err(Some)
+                        .map(|()| notion),
+                    Err(e) => Err(Some(e)),
+                },
+            };
+
+            match result {
+                Ok(_) if self.dry_run => {}
+                Ok(notion) => {
+                    green_ln!("Applied: {}", description);
+                    if let Some(id) = &task_id {
+                        self.rebase(notion.as_ref(), id, &mut pending);
+                    }
+                }
+                Err(e) => {
+                    if let Some(e) = e {
+                        red_ln!("Failed: {}: {}", description, e);
+                    }
+                    if let Some(id) = task_id {
+                        blocked.insert(id);
+                    }
+                    kept.push(entry);
+                }
+            }
+
+            // save after every change so an interrupted sync neither applies anything twice nor loses what's left
+            self.save(&kept, &pending)?;
+        }
+
+        if self.dry_run {
+            green_ln!("Dry run, the {} queued change(s) were kept", total);
+            return Ok(());
+        }
+
+        let failed = kept.len();
+        if failed == 0 {
+            green_ln!("Synced {} change(s)", total);
+        } else {
+            red_ln!(
+                "Synced {} of {} change(s), {} are still queued",
+                total - failed,
+                total,
+                failed
+            );
+        }
+
+        return Ok(());
+    }
+
+    /// Save the entries that stay queued followed by the ones not replayed yet, unless this is a dry run
+    fn save(&self, kept: &[JournalEntry], pending: &VecDeque<JournalEntry>) -> Result<()> {
+        if self.dry_run {
+            return Ok(());
+        }
+
+        let entries = kept.iter().chain(pending).cloned().collect();
+
+        return self.journal.set_journal(&Journal { entries });
+    }
+
+    /// After applying a change, compare the task's later changes against the edit just made
+    /// so they aren't reported as conflicts with our own change
+    fn rebase(
+        &self,
+        notion: &dyn NotionCaller,
+        task_id: &str,
+        pending: &mut VecDeque<JournalEntry>,
+    ) {
+        let mut later = pending
+            .iter_mut()
+            .filter(|e| e.operation.task_id() == Some(task_id))
+            .peekable();
+        if later.peek().is_none() {
+            return;
+        }
+
+        // if the task can't be read the later changes keep their base and may be reported as conflicts
+        let Ok(task) = notion.get_task(task_id) else {
+            return;
+        };
+        for entry in later {
+            entry.base_edited_time = Some(task.last_edited_time);
+        }
+    }
+}
+
+impl SyncCommandHandler for NotionSyncHandler {
+    fn sync(&self, databases: &[DatabaseConfig], force: bool, full: bool) -> Result<()> {
+        self.push(force)?;
+
+        // queued changes go first so the pulled tasks include them
+        for database in databases {
+            self.pull(database, full, databases.len() > 1)?;
+        }
+
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+    use crate::{
+        models::{property_mapping::PropertyMapping, task_status::TaskStatus},
+        services::fake::{task, time, FakeNotion, MemoryCache, MemoryJournal},
+    };
+
+    fn database(id: &str) -> DatabaseConfig {
+        return DatabaseConfig {
+            id: id.to_string(),
+            title: id.to_string(),
+            mapping: PropertyMapping::default(),
+            alias: None,
+        };
+    }
+
+    fn entry(operation: Operation, base: Option<i64>) -> JournalEntry {
+        return JournalEntry {
+            queued_at: Utc::now(),
+            base_edited_time: base.map(time),
+            database_id: None,
+            operation,
+        };
+    }
+
+    fn done(id: &str) -> Operation {
+        return Operation::MarkAsDone { id: id.to_string() };
+    }
+
+    fn start(id: &str) -> Operation {
+        return Operation::UpdateTask {
+            id: id.to_string(),
+            to: Some(TaskStatus::Doing),
+            name: None,
+        };
+    }
+
+    fn journal(entries: Vec<JournalEntry>) -> MemoryJournal {
+        let journal = MemoryJournal::default();
+        journal.set_journal(&Journal { entries }).unwrap();
+
+        return journal;
+    }
+
+    fn queued(journal: &MemoryJournal) -> Vec<String> {
+        return journal
+            .get_journal()
+            .unwrap()
+            .entries
+            .iter()
+            .map(|e| e.operation.describe())
+            .collect();
+    }
+
+    fn handler(notion: &FakeNotion, journal: &MemoryJournal, dry_run: bool) -> NotionSyncHandler {
+        let notion = notion.clone();
+
+        return NotionSyncHandler::new(
+            Box::new(move |_| Ok(Box::new(notion.clone()) as Box<dyn NotionCaller>)),
+            vec![database("db")],
+            Box::new(journal.clone()),
+            Box::new(MemoryCache::default()),
+            dry_run,
+        );
+    }
+
+    /// Task 1 was edited in Notion at minute 10, task 2 at minute 0
+    fn notion() -> FakeNotion {
+        return FakeNotion::with_tasks(vec![
+            task("1", "One", TaskStatus::Todo, 10),
+            task("2", "Two", TaskStatus::Todo, 0),
+        ]);
+    }
+
+    #[test]
+    fn conflicts_stay_queued() {
+        let notion = notion();
+        let journal = journal(vec![entry(start("1"), Some(5))]);
+
+        handler(&notion, &journal, false).push(false).unwrap();
+
+        assert!(notion.calls().is_empty());
+        assert_eq!(queued(&journal), vec!["update 1"]);
+    }
+
+    #[test]
+    fn force_applies_conflicts() {
+        let notion = notion();
+        let journal = journal(vec![entry(start("1"), Some(5))]);
+
+        handler(&notion, &journal, false).push(true).unwrap();
+
+        assert_eq!(notion.calls(), vec!["update 1"]);
+        assert!(queued(&journal).is_empty());
+    }
+
+    #[test]
+    fn later_changes_to_a_blocked_task_stay_queued_in_order() {
+        let notion = notion();
+        let journal = journal(vec![
+            entry(start("1"), Some(5)),
+            entry(done("2"), Some(0)),
+            entry(done("1"), Some(10)),
+            entry(
+                Operation::AddComment {
+                    id: "1".to_string(),
+                    text: "later".to_string(),
+                },
+                Some(10),
+            ),
+        ]);
+
+        handler(&notion, &journal, false).push(false).unwrap();
+
+        assert_eq!(notion.calls(), vec!["done 2"]);
+        assert_eq!(
+            queued(&journal),
+            vec!["update 1", "mark 1 as done", "comment on 1"]
+        );
+    }
+
+    #[test]
+    fn failed_changes_stay_queued() {
+        let notion = notion();
+        notion.0.borrow_mut().failing.push("1".to_string());
+        let journal = journal(vec![entry(done("1"), None), entry(done("2"), None)]);
+
+        handler(&notion, &journal, false).push(false).unwrap();
+
+        assert_eq!(notion.calls(), vec!["done 2"]);
+        assert_eq!(queued(&journal), vec!["mark 1 as done"]);
+    }
+
+    #[test]
+    fn applied_changes_are_not_conflicts_of_later_ones() {
+        let notion = notion();
+        let journal = journal(vec![entry(start("2"), Some(0)), entry(done("2"), Some(0))]);
+
+        handler(&notion, &journal, false).push(false).unwrap();
+
+        assert_eq!(notion.calls(), vec!["update 2", "done 2"]);
+        assert!(queued(&journal).is_empty());
+    }
+
+    #[test]
+    fn dry_run_keeps_the_journal() {
+        let notion = notion();
+        let journal = journal(vec![entry(start("1"), Some(5)), entry(done("2"), Some(0))]);
+        let before = journal.0.borrow().clone();
+
+        handler(&notion, &journal, true).push(false).unwrap();
+
+        assert_eq!(*journal.0.borrow(), before);
+    }
+
+    #[test]
+    fn changes_are_replayed_in_their_database() {
+        let (personal, work) = (notion(), notion());
+        let clients = [("db", personal.clone()), ("work", work.clone())];
+        let mut queued_in_work = entry(done("2"), None);
+        queued_in_work.database_id = Some("work".to_string());
+        let journal = journal(vec![entry(done("1"), None), queued_in_work]);
+
+        let handler = NotionSyncHandler::new(
+            Box::new(move |database| {
+                let (_, notion) = clients.iter().find(|(id, _)| *id == database.id).unwrap();
+                Ok(Box::new(notion.clone()) as Box<dyn NotionCaller>)
+            }),
+            vec![database("db"), database("work")],
+            Box::new(journal.clone()),
+            Box::new(MemoryCache::default()),
+            false,
+        );
+        handler.push(false).unwrap();
+
+        assert_eq!(personal.calls(), vec!["done 1"]);
+        assert_eq!(work.calls(), vec!["done 2"]);
+    }
+}
@@ -1,37 +1,316 @@
-use anyhow::{Ok, Result};
-use colour::{green_ln, red_ln};
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs::{self, File},
+    io::{self, IsTerminal, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail, Ok, Result};
+use chrono::{Duration, Utc};
+use colour::{green_ln, red_ln, yellow_ln};
+use serde_json::Map;
 
 use crate::{
-    models::task_status::TaskStatus,
-    traits::{NotionCaller, TaskHandler},
+    export, import, markdown,
+    models::{
+        config::DatabaseConfig,
+        list_output::ListOutput,
+        listing::Listing,
+        name_match,
+        task::{NewTask, Task},
+        task_format::TaskFormat,
+        task_selector::{self, TaskSelector},
+        task_status::TaskStatus,
+    },
+    prompt,
+    traits::{ListingService, NotionCaller, TaskHandler},
 };
 
-/// A task handler that wraps a Notion client
+/// Builds a Notion client for a database, each database has its own property mapping
+pub type ConnectDatabase = Box<dyn Fn(&DatabaseConfig) -> Result<Box<dyn NotionCaller>>>;
+
+/// A task handler that wraps a Notion client per database
 pub struct NotionAPITaskHandler {
-    /// The Notion client
-    notion: Box<dyn NotionCaller>,
+    /// Creates the Notion client for a database
+    connect: ConnectDatabase,
+    /// Remembers the last listing so tasks can be referred to by index
+    listing: Box<dyn ListingService>,
+    /// Whether the Notion client only prints the changes it would make
+    dry_run: bool,
 }
 
 impl NotionAPITaskHandler {
-    /// Construct a new NotionTaskHandler given a Notion API client
-    pub fn new(notion: Box<dyn NotionCaller>) -> NotionAPITaskHandler {
-        return NotionAPITaskHandler { notion };
+    /// Construct a new NotionTaskHandler given a way to create Notion API clients
+    pub fn new(
+        connect: ConnectDatabase,
+        listing: Box<dyn ListingService>,
+        dry_run: bool,
+    ) -> NotionAPITaskHandler {
+        return NotionAPITaskHandler {
+            connect,
+            listing,
+            dry_run,
+        };
+    }
+
+    /// Resolve a selector into the tasks it refers to, fails if nothing matches
+    fn resolve(
+        &self,
+        notion: &dyn NotionCaller,
+        database_id: &str,
+        selector: &TaskSelector,
+    ) -> Result<Vec<Task>> {
+        let mut tasks = if selector.ids.is_empty() {
+            notion.list_tasks(database_id, &selector.status)?
+        } else {
+            self.resolve_ids(notion, database_id, &read_stdin_ids(&selector.ids)?)?
+        };
+
+        if let Some(status) = selector.status {
+            tasks.retain(|t| t.status == status);
+        }
+        if let Some(filter) = &selector.filter {
+            tasks.retain(|t| filter.matches(t));
+        }
+        if let Some(name) = &selector.name {
+            tasks = self.select_by_name(tasks, name, selector.all)?;
+        }
+
+        if tasks.is_empty() {
+            bail!("No matching tasks found");
+        }
+
+        return Ok(tasks);
+    }
+
+    /// Fetch tasks by handle, see resolve_handle
+    fn resolve_ids(
+        &self,
+        notion: &dyn NotionCaller,
+        database_id: &str,
+        handles: &[String],
+    ) -> Result<Vec<Task>> {
+        let mut listed: Option<Vec<Task>> = None;
+        let mut tasks = Vec::with_capacity(handles.len());
+
+        for handle in handles {
+            let id = self.resolve_handle(notion, database_id, handle, &mut listed)?;
+
+            let known = listed.iter().flatten().find(|t| t.id == id);
+            match known {
+                Some(task) => tasks.push(task.clone()),
+                None => tasks.push(notion.get_task(&id)?),
+            }
+        }
+
+        return Ok(tasks);
+    }
+
+    /// Turn a full ID, an index from the last listing or a unique ID prefix into a full ID
+    /// The database is only listed for prefixes, and at most once across calls sharing listed
+    fn resolve_handle(
+        &self,
+        notion: &dyn NotionCaller,
+        database_id: &str,
+        handle: &str,
+        listed: &mut Option<Vec<Task>>,
+    ) -> Result<String> {
+        let handle = handle.trim();
+
+        if task_selector::is_full_id(handle) {
+            return Ok(handle.to_string());
+        }
+
+        if task_selector::is_index(handle) {
+            let listing = self.listing.get_listing()?;
+            if !listing.includes(database_id) {
+                bail!(
+                    "'{}' looks like a task number, run `notion tasks list` first",
+                    handle
+                );
+            }
+
+            let index: usize = handle.parse()?;
+            let Some(id) = index.checked_sub(1).and_then(|i| listing.task_ids.get(i)) else {
+                bail!(
+                    "there is no task {} in the last listing, it had {} task(s)",
+                    index,
+                    listing.task_ids.len()
+                );
+            };
+
+            let source = listing.database_of(index - 1);
+            if source != database_id {
+                bail!(
+                    "task {} of the last listing is in database {}, pass --database {}",
+                    index,
+                    source,
+                    source
+                );
+            }
+
+            return Ok(id.clone());
+        }
+
+        if listed.is_none() {
+            *listed = Some(notion.list_tasks(database_id, &None)?);
+        }
+        let all_tasks = listed.as_deref().unwrap_or_default();
+
+        return Ok(task_selector::find_by_id_prefix(all_tasks, handle)?
+            .id
+            .clone());
+    }
+
+    /// Narrow tasks down to the ones matching a name pattern, best match first
+    /// Every close match is returned when all is set, otherwise the user picks one of the best matches on a terminal
+    fn select_by_name(&self, tasks: Vec<Task>, pattern: &str, all: bool) -> Result<Vec<Task>> {
+        let ranked = name_match::rank_by_name(tasks, pattern)?;
+        let mut matches = match all {
+            true => name_match::all_matches(ranked),
+            false => name_match::best_matches(ranked),
+        };
+
+        if matches.is_empty() {
+            bail!("No tasks matching '{}' found", pattern);
+        }
+        if matches.len() == 1 || all {
+            return Ok(matches);
+        }
+
+        let titles: Vec<String> = matches
+            .iter()
+            .map(|t| format!("{} ({}, {})", t.title, t.status, t.id))
+            .collect();
+
+        if !io::stdin().is_terminal() {
+            bail!(
+                "'{}' matches {} tasks, narrow it down or pass --all:\n{}",
+                pattern,
+                matches.len(),
+                titles.join("\n")
+            );
+        }
+
+        let choice = prompt::pick(
+            &format!("'{}' matches {} tasks", pattern, matches.len()),
+            &titles,
+        )?;
+
+        return Ok(vec![matches.swap_remove(choice)]);
+    }
+
+    /// Report a successful change, unless it was only printed because of a dry run
+    /// or queued because Notion couldn't be reached
+    fn success(&self, notion: &dyn NotionCaller, message: &str) {
+        let queued = notion.queued_changes();
+        if self.dry_run {
+            green_ln!("Dry run, no changes were sent to Notion");
+        } else if queued > 0 {
+            yellow_ln!("{} change(s) were queued instead of sent to Notion", queued);
+        } else {
+            green_ln!("{}", message);
+        }
     }
 }
 
 impl TaskHandler for NotionAPITaskHandler {
-    fn add(&self, database_id: &str, title: &str, status: &TaskStatus) -> Result<()> {
-        let title = title.trim();
+    fn add(
+        &self,
+        database: &DatabaseConfig,
+        title: &str,
+        status: &TaskStatus,
+        body: &Option<String>,
+    ) -> Result<()> {
+        let notion = (self.connect)(database)?;
+        let blocks = match body {
+            Some(b) => markdown::markdown_to_blocks(b),
+            None => Vec::new(),
+        };
 
-        self.notion.add_task(database_id, title, status)?;
+        if title != "-" {
+            notion.add_task(&database.id, title.trim(), status, &blocks, &Map::new())?;
 
-        green_ln!("Task added!");
+            self.success(notion.as_ref(), "Task added!");
+
+            return Ok(());
+        }
+
+        let titles = prompt::read_lines()?;
+        if titles.is_empty() {
+            bail!("no task names were read from stdin");
+        }
+
+        let tasks: Vec<NewTask> = titles
+            .iter()
+            .map(|title| NewTask {
+                title: title.clone(),
+                status: *status,
+                body: blocks.clone(),
+                properties: Map::new(),
+            })
+            .collect();
+
+        let results = notion.add_tasks(&database.id, &tasks)?;
+        let mut failed = 0;
+        for (title, result) in titles.iter().zip(results) {
+            if let Err(e) = result {
+                red_ln!("Failed to add '{}': {}", title, e);
+                failed += 1;
+            }
+        }
+        if failed > 0 {
+            bail!("{} of {} task(s) couldn't be added", failed, titles.len());
+        }
+
+        self.success(notion.as_ref(), &format!("{} task(s) added!", titles.len()));
 
         return Ok(());
     }
 
-    fn list(&self, database_id: &str, status: &Option<TaskStatus>, with_id: &bool) -> Result<()> {
-        let tasks = self.notion.list_tasks(database_id, status)?;
+    fn list(
+        &self,
+        databases: &[DatabaseConfig],
+        status: &Option<TaskStatus>,
+        with_id: &bool,
+        output: ListOutput,
+    ) -> Result<()> {
+        let Some(first) = databases.first() else {
+            bail!("no database to list tasks from");
+        };
+
+        // prefixes have to be unique across every listed database, not just the listed tasks
+        let mut all_tasks: Vec<(&DatabaseConfig, Task)> = Vec::new();
+        for database in databases {
+            let notion = (self.connect)(database)?;
+            let tasks = notion.list_tasks(&database.id, &None)?;
+            all_tasks.extend(tasks.into_iter().map(|t| (database, t)));
+        }
+
+        let prefixes = task_selector::shortest_unique_prefixes(
+            &all_tasks
+                .iter()
+                .map(|(_, t)| t.id.as_str())
+                .collect::<Vec<_>>(),
+        );
+        let tasks: Vec<(&DatabaseConfig, &Task)> = all_tasks
+            .iter()
+            .filter(|(_, t)| status.is_none_or(|s| t.status == s))
+            .map(|(d, t)| (*d, t))
+            .collect();
+
+        self.listing.set_listing(Listing {
+            database_id: first.id.clone(),
+            task_ids: tasks.iter().map(|(_, t)| t.id.clone()).collect(),
+            database_ids: tasks.iter().map(|(d, _)| d.id.clone()).collect(),
+        })?;
+
+        if output == ListOutput::Ids {
+            for (_, task) in &tasks {
+                println!("{}", task.id);
+            }
+            return Ok(());
+        }
 
         match status {
             Some(s) => {
@@ -39,40 +318,406 @@ impl TaskHandler for NotionAPITaskHandler {
                     "Tasks: {} -----------------------------------------------",
                     s
                 );
-                for (i, task) in tasks.iter().enumerate() {
-                    task.print(i, false, *with_id);
-                }
-                red_ln!("----------------------------------------------------");
             }
             None => {
                 red_ln!("Tasks ----------------------------------------------");
-                for (i, task) in tasks.iter().enumerate() {
-                    task.print(i, true, *with_id);
-                }
-                red_ln!("----------------------------------------------------");
             }
         }
+        // the source is only worth showing when tasks come from several databases
+        let with_source = databases.len() > 1;
+        for (i, (database, task)) in tasks.iter().enumerate() {
+            let handle = format!("{} or {}", i + 1, prefixes[&task.id]);
+            let source = with_source.then_some(database.title.as_str());
+            task.print(i, Some(&handle), source, status.is_none(), *with_id);
+        }
+        red_ln!("----------------------------------------------------");
 
         return Ok(());
     }
 
-    fn done(&self, database_id: &str, ids: &[String], name: Option<&str>) -> Result<()> {
-        if let Some(n) = name {
-            let task = self.notion.get_task_from_name(database_id, n)?;
-            self.notion.mark_as_done(&[task.id])?;
-            green_ln!("{} marked as done", task.title);
-        } else {
-            self.notion.mark_as_done(ids)?;
+    fn done(&self, database: &DatabaseConfig, selector: &TaskSelector) -> Result<()> {
+        let notion = (self.connect)(database)?;
+        let tasks = self.resolve(notion.as_ref(), &database.id, selector)?;
+        let ids: Vec<String> = tasks.iter().map(|t| t.id.clone()).collect();
+
+        notion.mark_as_done(&ids)?;
+
+        let marked: Vec<String> = tasks
+            .iter()
+            .map(|t| format!("{} marked as done", t.title))
+            .collect();
+        self.success(notion.as_ref(), &marked.join("\n"));
+
+        return Ok(());
+    }
+
+    fn update(
+        &self,
+        database: &DatabaseConfig,
+        selector: &TaskSelector,
+        to: &Option<TaskStatus>,
+        title: &Option<String>,
+    ) -> Result<()> {
+        let notion = (self.connect)(database)?;
+        let tasks = self.resolve(notion.as_ref(), &database.id, selector)?;
+
+        if title.is_some() && tasks.len() > 1 {
+            bail!(
+                "--name can only be used with a single task, {} were selected",
+                tasks.len()
+            );
+        }
+
+        let ids: Vec<String> = tasks.iter().map(|t| t.id.clone()).collect();
+        notion.update_tasks(&ids, to, title)?;
+
+        self.success(
+            notion.as_ref(),
+            &format!("Successfully updated {} task(s)", tasks.len()),
+        );
+
+        return Ok(());
+    }
+
+    fn show(&self, database: &DatabaseConfig, selector: &TaskSelector) -> Result<()> {
+        let notion = (self.connect)(database)?;
+        let tasks = self.resolve(notion.as_ref(), &database.id, selector)?;
+
+        for (i, task) in tasks.iter().enumerate() {
+            if i > 0 {
+                println!();
+            }
+            task.print(i, None, None, true, true);
+
+            let body = notion.get_task_body(&task.id)?;
+            if !body.is_empty() {
+                println!("{}", markdown::blocks_to_markdown(&body));
+            }
+        }
+
+        return Ok(());
+    }
+
+    fn archive(
+        &self,
+        database: &DatabaseConfig,
+        selector: &TaskSelector,
+        older_than: &Option<Duration>,
+        yes: bool,
+    ) -> Result<()> {
+        let notion = (self.connect)(database)?;
+        let mut tasks = self.resolve(notion.as_ref(), &database.id, selector)?;
+
+        if let Some(age) = older_than {
+            let Some(cutoff) = Utc::now().checked_sub_signed(*age) else {
+                bail!("--older-than reaches back further than dates go");
+            };
+            tasks.retain(|task| task.last_edited_time < cutoff);
+        }
+
+        if tasks.is_empty() {
+            red_ln!("No matching tasks found");
+            return Ok(());
+        }
+
+        red_ln!("Tasks to archive -----------------------------------");
+        for (i, task) in tasks.iter().enumerate() {
+            task.print(i, None, None, true, true);
+        }
+        red_ln!("----------------------------------------------------");
+
+        if tasks.len() > 1
+            && !yes
+            && !self.dry_run
+            && !prompt::confirm(&format!("Archive {} tasks?", tasks.len()))?
+        {
+            red_ln!("Aborted");
+            return Ok(());
+        }
+
+        let ids: Vec<String> = tasks.iter().map(|t| t.id.clone()).collect();
+        notion.archive_tasks(&ids)?;
+
+        self.success(
+            notion.as_ref(),
+            &format!(
+                "{} task(s) archived, restore one with `notion tasks restore <ID>`",
+                tasks.len()
+            ),
+        );
+
+        return Ok(());
+    }
+
+    fn restore(&self, database: &DatabaseConfig, id: &str) -> Result<()> {
+        // prefixes are looked up in the database, which never lists archived tasks
+        let handle = id.trim();
+        if !task_selector::is_full_id(handle) && !task_selector::is_index(handle) {
+            bail!(
+                "'{}' isn't a full task ID, archived tasks can't be found by ID prefix, use the ID `tasks archive` printed",
+                handle
+            );
+        }
+
+        let notion = (self.connect)(database)?;
+        let id = self.resolve_handle(notion.as_ref(), &database.id, handle, &mut None)?;
+        notion.restore_task(&id)?;
+
+        self.success(notion.as_ref(), "Task restored!");
+
+        return Ok(());
+    }
+
+    fn comment(&self, database: &DatabaseConfig, id: &str, text: &str) -> Result<()> {
+        let notion = (self.connect)(database)?;
+        let id = self.resolve_handle(notion.as_ref(), &database.id, id, &mut None)?;
+        notion.add_comment(&id, text.trim())?;
+
+        self.success(notion.as_ref(), "Comment added!");
+
+        return Ok(());
+    }
+
+    fn comments(&self, database: &DatabaseConfig, id: &str) -> Result<()> {
+        let notion = (self.connect)(database)?;
+        let id = self.resolve_handle(notion.as_ref(), &database.id, id, &mut None)?;
+        let comments = notion.list_comments(&id)?;
+
+        if comments.is_empty() {
+            red_ln!("No comments found");
+            return Ok(());
+        }
+
+        red_ln!("Comments -------------------------------------------");
+        for (i, comment) in comments.iter().enumerate() {
+            comment.print(i);
+        }
+        red_ln!("----------------------------------------------------");
+
+        return Ok(());
+    }
+
+    fn import(
+        &self,
+        database: &DatabaseConfig,
+        file: &Path,
+        format: &Option<TaskFormat>,
+        id_column: &str,
+    ) -> Result<()> {
+        let notion = (self.connect)(database)?;
+        let Some(format) = format.or_else(|| TaskFormat::from_path(file)) else {
+            bail!(
+                "can't tell the format of {} from its extension, pass --format",
+                file.display()
+            );
         };
 
+        let rows = import::read_rows(&fs::read_to_string(file)?, format)?;
+        let schema = notion.get_database_schema(&database.id)?;
+
+        // values of a column that isn't a property aren't stored, so a later import can only match them by title
+        let id_property = schema
+            .keys()
+            .find(|name| import::same_name(name, id_column));
+        if id_property.is_none() && rows.iter().any(|row| row.get(id_column).is_some()) {
+            yellow_ln!(
+                "The {} column isn't a property of the database, so its values aren't saved and rows whose {} isn't a Notion task ID are matched by title",
+                id_column,
+                id_column
+            );
+        }
+
+        // existing tasks by lowercase title and by ID, the id column may hold a Notion ID or a property value
+        let mut titles: HashMap<String, String> = HashMap::new();
+        let mut ids: HashMap<String, String> = HashMap::new();
+        for task in notion.list_tasks(&database.id, &None)? {
+            titles.insert(task.title.to_lowercase(), task.id.clone());
+            if let Some(value) = id_property.and_then(|p| task.properties.get(p)) {
+                ids.insert(value.clone(), task.id.clone());
+            }
+            ids.insert(task.id.clone(), task.id);
+        }
+
+        // rows are checked first, so the new tasks can be sent together
+        let mut plans = Vec::with_capacity(rows.len());
+        let mut tasks: Vec<NewTask> = Vec::new();
+        let mut ignored = BTreeSet::new();
+
+        for (i, row) in rows.iter().enumerate() {
+            let n = i + 1;
+            let external_id = row.get(id_column);
+
+            let duplicate_of = external_id
+                .and_then(|id| ids.get(id))
+                .or_else(|| titles.get(&row.title.to_lowercase()));
+            if let Some(existing) = duplicate_of {
+                plans.push(RowPlan::Duplicate(existing.clone()));
+                continue;
+            }
+
+            let task = (|| -> Result<NewTask> {
+                if row.title.is_empty() {
+                    bail!("the row has no title");
+                }
+
+                let status = match &row.status {
+                    Some(s) => import::parse_status(s)?,
+                    None => TaskStatus::Todo,
+                };
+                let (properties, unknown) = import::to_properties(row, &schema)?;
+                ignored.extend(
+                    unknown
+                        .into_iter()
+                        .filter(|c| !import::same_name(c, id_column)),
+                );
+
+                return Ok(NewTask {
+                    title: row.title.clone(),
+                    status,
+                    body: Vec::new(),
+                    properties,
+                });
+            })();
+
+            match task {
+                Result::Ok(task) => {
+                    plans.push(RowPlan::Add);
+                    tasks.push(task);
+
+                    // later rows of the same file are duplicates too
+                    titles.insert(row.title.to_lowercase(), format!("row {}", n));
+                    if let Some(id) = external_id {
+                        ids.insert(id.to_string(), format!("row {}", n));
+                    }
+                }
+                Err(e) => plans.push(RowPlan::Invalid(e)),
+            }
+        }
+
+        // the results come back in the order of the added rows
+        let mut results = notion.add_tasks(&database.id, &tasks)?.into_iter();
+
+        let verb = if self.dry_run { "Would add" } else { "Added" };
+        let (mut added, mut skipped, mut failed) = (0, 0, 0);
+
+        for (i, (row, plan)) in rows.iter().zip(plans).enumerate() {
+            let n = i + 1;
+            let result = match plan {
+                RowPlan::Duplicate(existing) => {
+                    yellow_ln!(
+                        "Row {}: skipped '{}', duplicate of {}",
+                        n,
+                        row.title,
+                        existing
+                    );
+                    skipped += 1;
+                    continue;
+                }
+                RowPlan::Invalid(e) => Err(e),
+                RowPlan::Add => results
+                    .next()
+                    .unwrap_or_else(|| Err(anyhow!("no result was returned for the row"))),
+            };
+
+            match result {
+                Result::Ok(()) => {
+                    green_ln!("Row {}: {} '{}'", n, verb, row.title);
+                    added += 1;
+                }
+                Err(e) => {
+                    red_ln!("Row {}: failed '{}': {}", n, row.title, e);
+                    failed += 1;
+                }
+            }
+        }
+
+        if !ignored.is_empty() {
+            yellow_ln!(
+                "These columns aren't properties of the database and were ignored: {}",
+                ignored.into_iter().collect::<Vec<_>>().join(", ")
+            );
+        }
+        if self.dry_run {
+            green_ln!("Dry run, no changes were sent to Notion");
+        } else if notion.queued_changes() > 0 {
+            yellow_ln!(
+                "{} task(s) were queued instead of sent to Notion",
+                notion.queued_changes()
+            );
+        }
+        green_ln!(
+            "{} {} task(s), skipped {} duplicate(s), {} failed",
+            verb,
+            added,
+            skipped,
+            failed
+        );
+
         return Ok(());
     }
 
-    fn update(&self, id: &str, to: &Option<TaskStatus>, name: &Option<String>) -> Result<()> {
-        self.notion.update_task(id, to, name)?;
+    fn export(
+        &self,
+        database: &DatabaseConfig,
+        format: TaskFormat,
+        out: &Option<PathBuf>,
+    ) -> Result<()> {
+        let notion = (self.connect)(database)?;
+        let tasks = notion.list_tasks(&database.id, &None)?;
+
+        let Some(path) = out else {
+            return export::write_tasks(&tasks, format, &mut io::stdout().lock());
+        };
 
-        green_ln!("Successfully updated task");
+        let mut file = File::create(path)?;
+        export::write_tasks(&tasks, format, &mut file)?;
+        file.flush()?;
+
+        green_ln!(
+            "Exported {} task(s) to {} as {}",
+            tasks.len(),
+            path.display(),
+            format
+        );
 
         return Ok(());
     }
 }
+
+/// What an import does with a row
+enum RowPlan {
+    /// The row is a duplicate of this existing task or earlier row
+    Duplicate(String),
+    /// The row can't be turned into a task
+    Invalid(anyhow::Error),
+    /// The row is added as a new task
+    Add,
+}
+
+/// Replace a "-" among the handles with the lines of stdin
+/// "ID: " prefixes are dropped so `tasks list --with-id | grep ID` can be piped in
+fn read_stdin_ids(handles: &[String]) -> Result<Vec<String>> {
+    if !handles.iter().any(|h| h == "-") {
+        return Ok(handles.to_vec());
+    }
+
+    let lines = prompt::read_lines()?;
+    if lines.is_empty() {
+        bail!("no task IDs were read from stdin");
+    }
+
+    let mut ids = Vec::with_capacity(handles.len() + lines.len());
+    for handle in handles {
+        if handle == "-" {
+            ids.extend(
+                lines
+                    .iter()
+                    .map(|l| l.strip_prefix("ID:").unwrap_or(l).trim().to_string()),
+            );
+        } else {
+            ids.push(handle.clone());
+        }
+    }
+
+    return Ok(ids);
+}
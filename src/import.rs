@@ -0,0 +1,588 @@
+use anyhow::{bail, Result};
+use clap::ValueEnum;
+use serde_json::{json, Map, Value};
+
+use crate::{
+    markdown,
+    models::{api::schema::Schema, task_format::TaskFormat, task_status::TaskStatus},
+};
+
+/// A task read from an import file
+pub struct ImportRow {
+    /// The task title
+    pub title: String,
+    /// The task status as written in the file, if it had one
+    pub status: Option<String>,
+    /// The other columns, in file order
+    pub columns: Vec<(String, String)>,
+}
+
+impl ImportRow {
+    /// The value of a column, column names are matched like property names
+    pub fn get(&self, column: &str) -> Option<&str> {
+        return self
+            .columns
+            .iter()
+            .find(|(name, value)| same_name(name, column) && !value.is_empty())
+            .map(|(_, value)| value.as_str());
+    }
+}
+
+/// Columns that hold the task title
+const TITLE_COLUMNS: [&str; 2] = ["title", "name"];
+
+/// Columns exports write that can't be set on a new page
+const READ_ONLY_COLUMNS: [&str; 2] = ["last_edited_time", "archived"];
+
+/// Parse the rows of an import file
+pub fn read_rows(text: &str, format: TaskFormat) -> Result<Vec<ImportRow>> {
+    return match format {
+        TaskFormat::Csv => read_csv(text),
+        TaskFormat::Json => read_json(text),
+        TaskFormat::Markdown => Ok(read_markdown(text)),
+        TaskFormat::Todotxt => Ok(read_todotxt(text)),
+    };
+}
+
+/// Parse a status written as a CLI value (todo) or a Notion option name (To Do)
+pub fn parse_status(status: &str) -> Result<TaskStatus> {
+    let normalized: String = status
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .collect::<String>()
+        .to_lowercase();
+
+    return match TaskStatus::from_str(&normalized, true) {
+        Ok(status) => Ok(status),
+        Err(_) => bail!("unknown status '{}', use todo, doing or done", status),
+    };
+}
+
+/// Turn a row's columns into Notion property values using the database schema
+/// Returns the values along with the columns that aren't properties of the database
+pub fn to_properties(
+    row: &ImportRow,
+    schema: &Schema,
+) -> Result<(Map<String, Value>, Vec<String>)> {
+    let mut properties = Map::new();
+    let mut ignored = Vec::new();
+
+    for (column, value) in &row.columns {
+        if value.is_empty() || READ_ONLY_COLUMNS.iter().any(|c| same_name(c, column)) {
+            continue;
+        }
+
+        let found = schema.iter().find(|(name, _)| same_name(name, column));
+        let Some((name, property)) = found else {
+            ignored.push(column.clone());
+            continue;
+        };
+
+        let kind = property.kind();
+        // the title and status are set from the row itself
+        if kind == "title" || same_name(name, "status") {
+            continue;
+        }
+
+        let value = match property_value(kind, value) {
+            Ok(v) => v,
+            Err(e) => bail!("column '{}': {}", column, e),
+        };
+        properties.insert(name.clone(), value);
+    }
+
+    return Ok((properties, ignored));
+}
+
+/// Build the value of a property of the given type from its plain text form
+fn property_value(kind: &str, value: &str) -> Result<Value> {
+    let split = |v: &str| -> Vec<Value> {
+        return v
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|s| json!({ "name": s }))
+            .collect();
+    };
+
+    return Ok(match kind {
+        "rich_text" => json!([markdown::plain_rich_text(value)]),
+        "number" => match value.trim().parse::<f64>() {
+            Ok(n) => json!(n),
+            Err(_) => bail!("'{}' isn't a number", value),
+        },
+        "select" | "status" => json!({ "name": value.trim() }),
+        "multi_select" => json!(split(value)),
+        "date" => match value.split_once('/') {
+            Some((start, end)) => json!({ "start": start.trim(), "end": end.trim() }),
+            None => json!({ "start": value.trim() }),
+        },
+        "checkbox" => match value.trim().to_lowercase().as_str() {
+            "true" | "yes" | "x" | "1" => json!(true),
+            "false" | "no" | "" | "0" => json!(false),
+            _ => bail!("'{}' isn't true or false", value),
+        },
+        "url" | "email" | "phone_number" => json!(value.trim()),
+        _ => bail!("{} properties can't be imported", kind),
+    });
+}
+
+/// Whether two column or property names refer to the same thing
+/// Case, spaces, dashes and underscores are ignored, so "due-date" matches "Due Date"
+pub fn same_name(a: &str, b: &str) -> bool {
+    let normalize = |s: &str| -> String {
+        return s
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != '-' && *c != '_')
+            .flat_map(char::to_lowercase)
+            .collect();
+    };
+
+    return normalize(a) == normalize(b);
+}
+
+/// Build a row from named columns, pulling out the title and status
+fn row_from_columns(columns: Vec<(String, String)>) -> ImportRow {
+    let mut row = ImportRow {
+        title: String::new(),
+        status: None,
+        columns: Vec::with_capacity(columns.len()),
+    };
+
+    for (name, value) in columns {
+        if row.title.is_empty() && TITLE_COLUMNS.iter().any(|c| same_name(c, &name)) {
+            row.title = value.trim().to_string();
+        } else if row.status.is_none() && same_name(&name, "status") && !value.is_empty() {
+            row.status = Some(value);
+        } else {
+            row.columns.push((name, value));
+        }
+    }
+
+    return row;
+}
+
+/// A header row then one row per task
+fn read_csv(text: &str) -> Result<Vec<ImportRow>> {
+    let mut reader = csv::Reader::from_reader(text.as_bytes());
+    let headers = reader.headers()?.clone();
+
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record?;
+        let columns = headers
+            .iter()
+            .zip(record.iter())
+            .map(|(h, v)| (h.to_string(), v.to_string()))
+            .collect();
+
+        rows.push(row_from_columns(columns));
+    }
+
+    return Ok(rows);
+}
+
+/// An array of objects, the nested properties object written by exports is flattened into columns
+fn read_json(text: &str) -> Result<Vec<ImportRow>> {
+    let tasks: Vec<Map<String, Value>> = serde_json::from_str(text)?;
+
+    let mut rows = Vec::with_capacity(tasks.len());
+    for task in tasks {
+        let mut columns = Vec::new();
+
+        for (name, value) in task {
+            match value {
+                Value::Object(properties) if name == "properties" => {
+                    for (name, value) in properties {
+                        columns.push((name, json_to_string(value)));
+                    }
+                }
+                value => columns.push((name, json_to_string(value))),
+            }
+        }
+
+        rows.push(row_from_columns(columns));
+    }
+
+    return Ok(rows);
+}
+
+/// Plain text form of a JSON value, arrays are joined with ", "
+fn json_to_string(value: Value) -> String {
+    return match value {
+        Value::Null => String::new(),
+        Value::String(s) => s,
+        Value::Array(values) => values
+            .into_iter()
+            .map(json_to_string)
+            .collect::<Vec<_>>()
+            .join(", "),
+        other => other.to_string(),
+    };
+}
+
+/// A checklist, "## status" headings set the status of the unchecked items under them
+/// and "key: value" items nested under a task become its columns
+fn read_markdown(text: &str) -> Vec<ImportRow> {
+    let mut rows: Vec<ImportRow> = Vec::new();
+    let mut section: Option<String> = None;
+
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        let nested = trimmed.len() < line.len();
+
+        if let Some(heading) = trimmed.strip_prefix("## ") {
+            section = parse_status(heading)
+                .ok()
+                .map(|_| heading.trim().to_string());
+            continue;
+        }
+
+        let Some(item) = trimmed
+            .strip_prefix("- ")
+            .or_else(|| trimmed.strip_prefix("* "))
+        else {
+            continue;
+        };
+
+        let checkbox = item
+            .strip_prefix("[ ] ")
+            .map(|title| (false, title))
+            .or_else(|| {
+                item.strip_prefix("[x] ")
+                    .or_else(|| item.strip_prefix("[X] "))
+                    .map(|title| (true, title))
+            });
+
+        match (checkbox, rows.last_mut()) {
+            (Some((checked, title)), _) => rows.push(ImportRow {
+                title: title.trim().to_string(),
+                status: if checked {
+                    Some(TaskStatus::Done.to_string())
+                } else {
+                    section.clone()
+                },
+                columns: Vec::new(),
+            }),
+            (None, Some(row)) if nested => {
+                if let Some((name, value)) = item.split_once(':') {
+                    row.columns
+                        .push((name.trim().to_string(), value.trim().to_string()));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    return rows;
+}
+
+/// One task per line, "x " marks done tasks and key:value tags become columns
+/// The priority and completion/creation dates are dropped, +projects and @contexts stay in the title
+fn read_todotxt(text: &str) -> Vec<ImportRow> {
+    let mut rows = Vec::new();
+
+    for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        let (done, rest) = match line.strip_prefix("x ") {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+
+        let mut words = Vec::new();
+        let mut columns = Vec::new();
+        let mut status = done.then(|| TaskStatus::Done.to_string());
+
+        for (i, word) in rest.split_whitespace().enumerate() {
+            // the leading words before the description are the priority and dates
+            let leading = words.is_empty() && columns.is_empty() && i < 3;
+            if leading && (is_date(word) || is_priority(word)) {
+                continue;
+            }
+
+            match word.split_once(':') {
+                Some((key, value))
+                    if !key.is_empty() && !value.is_empty() && !value.starts_with("//") =>
+                {
+                    if key == "status" {
+                        status = Some(value.to_string());
+                    } else {
+                        columns.push((key.to_string(), value.to_string()));
+                    }
+                }
+                _ => words.push(word),
+            }
+        }
+
+        rows.push(ImportRow {
+            title: words.join(" "),
+            status,
+            columns,
+        });
+    }
+
+    return rows;
+}
+
+/// Whether a todo.txt word is a priority like (A)
+fn is_priority(word: &str) -> bool {
+    let bytes = word.as_bytes();
+    return bytes.len() == 3
+        && bytes[0] == b'('
+        && bytes[1].is_ascii_uppercase()
+        && bytes[2] == b')';
+}
+
+/// Whether a todo.txt word is a YYYY-MM-DD date
+fn is_date(word: &str) -> bool {
+    return chrono::NaiveDate::parse_from_str(word, "%Y-%m-%d").is_ok();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use chrono::{TimeZone, Utc};
+
+    use super::*;
+    use crate::{export, models::task::Task};
+
+    fn columns(row: &ImportRow) -> Vec<(&str, &str)> {
+        return row
+            .columns
+            .iter()
+            .map(|(n, v)| (n.as_str(), v.as_str()))
+            .collect();
+    }
+
+    fn schema() -> Schema {
+        return serde_json::from_value(json!({
+            "Name": { "type": "title" },
+            "Status": { "type": "select" },
+            "Points": { "type": "number" },
+            "Tags": { "type": "multi_select" },
+            "Due Date": { "type": "date" },
+            "Urgent": { "type": "checkbox" },
+            "Owner": { "type": "people" },
+        }))
+        .unwrap();
+    }
+
+    #[test]
+    fn parses_statuses() {
+        assert_eq!(parse_status("todo").unwrap(), TaskStatus::Todo);
+        assert_eq!(parse_status("To Do").unwrap(), TaskStatus::Todo);
+        assert_eq!(parse_status(" DOING ").unwrap(), TaskStatus::Doing);
+        assert_eq!(parse_status("Done").unwrap(), TaskStatus::Done);
+        assert!(parse_status("in progress").is_err());
+    }
+
+    #[test]
+    fn names_ignore_case_spaces_dashes_and_underscores() {
+        assert!(same_name("Due Date", "due-date"));
+        assert!(same_name("due_date", "DueDate"));
+        assert!(!same_name("Due", "Due Date"));
+    }
+
+    #[test]
+    fn csv_rows_take_the_title_and_status_columns() {
+        let rows = read_rows(
+            "Name,Status,Due date,Points\nWrite report,Doing,2024-01-01,\n",
+            TaskFormat::Csv,
+        )
+        .unwrap();
+
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].title, "Write report");
+        assert_eq!(rows[0].status.as_deref(), Some("Doing"));
+        assert_eq!(
+            columns(&rows[0]),
+            vec![("Due date", "2024-01-01"), ("Points", "")]
+        );
+        assert_eq!(rows[0].get("due-date"), Some("2024-01-01"));
+        assert_eq!(rows[0].get("points"), None);
+    }
+
+    #[test]
+    fn json_properties_are_flattened() {
+        let rows = read_rows(
+            r#"[{"title": "Ship", "properties": {"Tags": ["a", "b"], "Points": 3, "Owner": null}}]"#,
+            TaskFormat::Json,
+        )
+        .unwrap();
+
+        assert_eq!(rows[0].title, "Ship");
+        assert_eq!(rows[0].status, None);
+        assert_eq!(
+            columns(&rows[0]),
+            vec![("Owner", ""), ("Points", "3"), ("Tags", "a, b")]
+        );
+        assert!(read_rows("{}", TaskFormat::Json).is_err());
+    }
+
+    #[test]
+    fn markdown_sections_set_the_status_of_unchecked_items() {
+        let rows = read_rows(
+            "# Tasks\n\n## Doing\n\n- [ ] Write\n  - due: tomorrow\n  - not a column\n* [X] Shipped\n\n## Notes\n\n- [ ] Read\n- a plain item\n",
+            TaskFormat::Markdown,
+        )
+        .unwrap();
+
+        let summary: Vec<(&str, Option<&str>)> = rows
+            .iter()
+            .map(|r| (r.title.as_str(), r.status.as_deref()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("Write", Some("Doing")),
+                ("Shipped", Some("done")),
+                ("Read", None)
+            ]
+        );
+        assert_eq!(columns(&rows[0]), vec![("due", "tomorrow")]);
+    }
+
+    #[test]
+    fn todotxt_drops_priorities_and_dates() {
+        let rows = read_rows(
+            "(A) 2024-01-02 Call mom +family @phone due:2024-02-01 http://example.com\n\nx 2024-01-03 2024-01-01 Pay rent 2024-01-05\nBuy milk status:doing\n",
+            TaskFormat::Todotxt,
+        )
+        .unwrap();
+
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].title, "Call mom +family @phone http://example.com");
+        assert_eq!(rows[0].status, None);
+        assert_eq!(columns(&rows[0]), vec![("due", "2024-02-01")]);
+        // only the leading dates are dropped
+        assert_eq!(rows[1].title, "Pay rent 2024-01-05");
+        assert_eq!(rows[1].status.as_deref(), Some("done"));
+        assert_eq!(rows[2].title, "Buy milk");
+        assert_eq!(rows[2].status.as_deref(), Some("doing"));
+    }
+
+    #[test]
+    fn property_values_are_coerced_to_their_type() {
+        assert_eq!(property_value("number", " 2.5 ").unwrap(), json!(2.5));
+        assert!(property_value("number", "lots").is_err());
+        assert_eq!(
+            property_value("multi_select", "a, ,b").unwrap(),
+            json!([{ "name": "a" }, { "name": "b" }])
+        );
+        assert_eq!(
+            property_value("date", "2024-01-01 / 2024-01-03").unwrap(),
+            json!({ "start": "2024-01-01", "end": "2024-01-03" })
+        );
+        assert_eq!(property_value("checkbox", "Yes").unwrap(), json!(true));
+        assert_eq!(property_value("checkbox", "0").unwrap(), json!(false));
+        assert!(property_value("checkbox", "maybe").is_err());
+        assert_eq!(
+            property_value("select", " High ").unwrap(),
+            json!({ "name": "High" })
+        );
+        assert!(property_value("people", "me").is_err());
+    }
+
+    #[test]
+    fn columns_become_properties_of_the_schema() {
+        let row = row_from_columns(vec![
+            ("name".to_string(), "Write".to_string()),
+            ("status".to_string(), "todo".to_string()),
+            ("points".to_string(), "3".to_string()),
+            ("due-date".to_string(), "2024-01-01".to_string()),
+            ("urgent".to_string(), String::new()),
+            (
+                "last_edited_time".to_string(),
+                "2024-01-01T00:00:00Z".to_string(),
+            ),
+            ("color".to_string(), "red".to_string()),
+        ]);
+
+        let (properties, ignored) = to_properties(&row, &schema()).unwrap();
+
+        assert_eq!(
+            Value::Object(properties),
+            json!({ "Points": 3.0, "Due Date": { "start": "2024-01-01" } })
+        );
+        assert_eq!(ignored, vec!["color"]);
+
+        let row = row_from_columns(vec![("Points".to_string(), "many".to_string())]);
+        let error = to_properties(&row, &schema()).unwrap_err().to_string();
+        assert!(error.contains("column 'Points'"), "{}", error);
+    }
+
+    /// Tasks with uneven properties, values without spaces so they survive todo.txt
+    fn exported_tasks() -> Vec<Task> {
+        let edited = Utc.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap();
+
+        return vec![
+            Task::new(
+                "1".to_string(),
+                TaskStatus::Todo,
+                "Write report".to_string(),
+                edited,
+                false,
+                BTreeMap::from([("Points".to_string(), "3".to_string())]),
+            ),
+            Task::new(
+                "2".to_string(),
+                TaskStatus::Doing,
+                "Review".to_string(),
+                edited,
+                false,
+                BTreeMap::new(),
+            ),
+            Task::new(
+                "3".to_string(),
+                TaskStatus::Done,
+                "Ship".to_string(),
+                edited,
+                false,
+                BTreeMap::from([("Tags".to_string(), "release".to_string())]),
+            ),
+        ];
+    }
+
+    /// Export the tasks, import them again and compare what survived
+    fn round_trip(format: TaskFormat) {
+        let tasks = exported_tasks();
+        let mut out = Vec::new();
+        export::write_tasks(&tasks, format, &mut out).unwrap();
+        let rows = read_rows(&String::from_utf8(out).unwrap(), format).unwrap();
+
+        assert_eq!(rows.len(), tasks.len(), "{}", format);
+        for task in &tasks {
+            let row = rows
+                .iter()
+                .find(|r| r.get("id") == Some(task.id.as_str()))
+                .unwrap_or_else(|| panic!("{}: task {} wasn't imported", format, task.id));
+
+            assert_eq!(row.title, task.title, "{}", format);
+            let status = parse_status(row.status.as_deref().unwrap()).unwrap();
+            assert_eq!(status, task.status, "{}", format);
+            for (name, value) in &task.properties {
+                assert_eq!(row.get(name), Some(value.as_str()), "{}", format);
+            }
+        }
+    }
+
+    #[test]
+    fn csv_round_trip() {
+        round_trip(TaskFormat::Csv);
+    }
+
+    #[test]
+    fn json_round_trip() {
+        round_trip(TaskFormat::Json);
+    }
+
+    #[test]
+    fn markdown_round_trip() {
+        round_trip(TaskFormat::Markdown);
+    }
+
+    #[test]
+    fn todotxt_round_trip() {
+        round_trip(TaskFormat::Todotxt);
+    }
+}
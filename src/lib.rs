@@ -0,0 +1,49 @@
+#![allow(clippy::needless_return)]
+#![warn(missing_docs)]
+
+//! Task management with notion, as a library and the `notion` command line app
+//!
+//! The pieces other programs can build on:
+//! - [`NotionAPI`], a blocking client, and [`NotionClient`], the async client it wraps, use the latter inside a tokio runtime
+//! - [`NotionCaller`], the task operations both the client and the offline cache implement
+//! - [`models`], tasks, statuses and the typed Notion API objects
+//! - [`AppConfig`] and [`JSONConfigService`], the config the app saves in ~/.notion-cli
+//!
+//! ```no_run
+//! use notion::{models::property_mapping::PropertyMapping, NotionAPI, NotionCaller};
+//!
+//! let notion = NotionAPI::new(
+//!     "https://api.notion.com".to_string(),
+//!     "secret_token".to_string(),
+//!     PropertyMapping::default(),
+//!     false,
+//! )?;
+//! for task in notion.list_tasks("database_id", &None)? {
+//!     println!("{}", task.title);
+//! }
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+mod cli;
+mod completions;
+mod export;
+mod handlers;
+mod import;
+mod manual;
+/// Converts between markdown and Notion blocks
+pub mod markdown;
+/// Tasks, statuses and the Notion objects they are read from
+pub mod models;
+mod prompt;
+/// The Notion client and the files the app keeps in ~/.notion-cli
+pub mod services;
+/// The operations the client, handlers and storage implement
+pub mod traits;
+
+pub use cli::Cli;
+pub use models::{config::AppConfig, task::Task, task_status::TaskStatus};
+pub use services::{
+    config::JSONConfigService,
+    notion::{NotionAPI, NotionClient},
+};
+pub use traits::{ConfigService, NotionCaller};
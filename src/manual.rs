@@ -0,0 +1,108 @@
+use std::{fs::File, io::Write, path::Path};
+
+use anyhow::Result;
+use clap_mangen::Man;
+
+/// The heading examples are listed under in a command's after_help
+const EXAMPLES_HEADING: &str = "EXAMPLES:";
+
+/// Write a man page for the command and one for each of its subcommands, named like notion-tasks-add.1
+/// Returns the number of pages written
+pub fn write_pages(cmd: clap::Command, dir: &Path) -> Result<usize> {
+    let mut written = 0;
+    let mut pending = vec![prepare(cmd)];
+
+    while let Some(cmd) = pending.pop() {
+        let mut file = File::create(dir.join(format!("{}.1", cmd.get_name())))?;
+        write_page(&cmd, &mut file)?;
+        written += 1;
+
+        for sub in visible_subcommands(&cmd) {
+            let name = format!("{}-{}", cmd.get_name(), sub.get_name());
+            pending.push(sub.clone().name(name));
+        }
+    }
+
+    return Ok(written);
+}
+
+/// Write the man page of the top level command
+pub fn write_root_page(cmd: clap::Command, out: &mut dyn Write) -> Result<()> {
+    return write_page(&prepare(cmd), out);
+}
+
+/// Name the command after the binary and propagate global options to subcommands
+fn prepare(cmd: clap::Command) -> clap::Command {
+    let mut cmd = cmd.name("notion");
+    cmd.build();
+
+    return cmd;
+}
+
+/// Write the man page of a single command, examples in its after_help get their own section
+fn write_page(cmd: &clap::Command, out: &mut dyn Write) -> Result<()> {
+    // the generated help subcommand doesn't get a page, so it isn't listed either
+    let mut cmd = cmd.clone();
+    if cmd.find_subcommand("help").is_some() {
+        cmd = cmd.mut_subcommand("help", |s| s.hide(true));
+    }
+
+    let man = Man::new(cmd.clone());
+
+    man.render_title(out)?;
+    man.render_name_section(out)?;
+    man.render_synopsis_section(out)?;
+    man.render_description_section(out)?;
+    if cmd.get_arguments().any(|a| !a.is_hide_set()) {
+        man.render_options_section(out)?;
+    }
+    if visible_subcommands(&cmd).next().is_some() {
+        man.render_subcommands_section(out)?;
+    }
+    if let Some(examples) = cmd.get_after_help() {
+        write_examples(examples, out)?;
+    }
+    if cmd.get_author().is_some() {
+        man.render_authors_section(out)?;
+    }
+
+    return Ok(());
+}
+
+/// Subcommands that get a page of their own
+fn visible_subcommands<'a, 'help>(
+    cmd: &'a clap::Command<'help>,
+) -> impl Iterator<Item = &'a clap::Command<'help>> {
+    return cmd
+        .get_subcommands()
+        .filter(|s| !s.is_hide_set() && s.get_name() != "help");
+}
+
+/// Render an after_help examples list, description lines followed by indented notion commands, as an EXAMPLES section
+fn write_examples(examples: &str, out: &mut dyn Write) -> Result<()> {
+    let Some(examples) = examples.trim().strip_prefix(EXAMPLES_HEADING) else {
+        return Ok(());
+    };
+
+    writeln!(out, ".SH EXAMPLES")?;
+    for line in examples.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        if line.starts_with("notion ") {
+            writeln!(out, ".RS 4\n.nf\n{}\n.fi\n.RE", escape(line))?;
+        } else {
+            writeln!(out, ".PP\n{}", escape(line))?;
+        }
+    }
+
+    return Ok(());
+}
+
+/// Escape text so roff prints it as is
+fn escape(line: &str) -> String {
+    let line = line.replace('\\', "\\e").replace('-', "\\-");
+
+    if line.starts_with('.') || line.starts_with('\'') {
+        return format!("\\&{}", line);
+    }
+
+    return line;
+}
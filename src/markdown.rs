@@ -0,0 +1,595 @@
+//! Two-way conversion between Markdown and Notion block JSON
+//!
+//! Supported blocks: paragraphs, headings (1-3), bulleted, numbered and to-do lists (nested),
+//! quotes, fenced code and dividers. Rich text keeps bold, italic, strikethrough, inline code and links.
+
+use pulldown_cmark::{CodeBlockKind, Event, HeadingLevel, Options, Parser, Tag};
+use serde_json::{json, Value};
+
+/// The most characters Notion accepts in the content of a single rich text object
+pub const MAX_TEXT_LENGTH: usize = 2000;
+
+/// Convert a markdown document into a list of Notion blocks
+/// Nested list items are placed in the `children` field of their parent block
+pub fn markdown_to_blocks(markdown: &str) -> Vec<Value> {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    options.insert(Options::ENABLE_TASKLISTS);
+
+    let mut builder = BlockBuilder::new();
+    for event in Parser::new_ext(markdown, options) {
+        builder.handle(event);
+    }
+
+    return builder.finish();
+}
+
+/// Render a list of Notion blocks as markdown
+/// Unsupported block types are skipped
+pub fn blocks_to_markdown(blocks: &[Value]) -> String {
+    let mut out = String::new();
+    write_blocks(&mut out, blocks, "");
+
+    return out;
+}
+
+/// Render a list of rich text objects as inline markdown
+pub fn rich_text_to_markdown(rich_text: &[Value]) -> String {
+    let mut out = String::new();
+
+    for segment in rich_text {
+        let content = segment["text"]["content"]
+            .as_str()
+            .or_else(|| segment["plain_text"].as_str())
+            .unwrap_or_default();
+        if content.is_empty() {
+            continue;
+        }
+
+        let annotations = &segment["annotations"];
+        let flag = |name: &str| annotations[name].as_bool().unwrap_or(false);
+        let link = segment["text"]["link"]["url"]
+            .as_str()
+            .or_else(|| segment["href"].as_str());
+
+        // markers can't hug whitespace, so keep it outside of them
+        let trimmed = content.trim();
+        let start = content.len() - content.trim_start().len();
+        let (leading, trailing) = (&content[..start], &content[start + trimmed.len()..]);
+
+        let mut text = if flag("code") {
+            format!("`{}`", trimmed)
+        } else {
+            escape(trimmed)
+        };
+        if !trimmed.is_empty() {
+            if flag("italic") {
+                text = format!("_{}_", text);
+            }
+            if flag("bold") {
+                text = format!("**{}**", text);
+            }
+            if flag("strikethrough") {
+                text = format!("~~{}~~", text);
+            }
+            if let Some(url) = link {
+                text = format!("[{}]({})", text, url);
+            }
+        }
+
+        out.push_str(leading);
+        out.push_str(&text);
+        out.push_str(trailing);
+    }
+
+    return out;
+}
+
+/// Build a single plain rich text object
+pub fn plain_rich_text(content: &str) -> Value {
+    return rich_text(content, &Annotations::default(), None);
+}
+
+/// Escape characters that would otherwise be read as markdown syntax
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '*' | '_' | '`' | '~' | '[' | ']') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+
+    return out;
+}
+
+/// Write blocks to out, prefixing every line with indent
+fn write_blocks(out: &mut String, blocks: &[Value], indent: &str) {
+    let mut previous: Option<&str> = None;
+    let mut number = 0;
+
+    for block in blocks {
+        let kind = block["type"].as_str().unwrap_or_default();
+        let data = &block[kind];
+        let text = rich_text_to_markdown(data["rich_text"].as_array().map_or(&[], |v| v));
+
+        let line = match kind {
+            "paragraph" => text,
+            "heading_1" => format!("# {}", text),
+            "heading_2" => format!("## {}", text),
+            "heading_3" => format!("### {}", text),
+            "bulleted_list_item" => format!("- {}", text),
+            "numbered_list_item" => {
+                number = if previous == Some(kind) {
+                    number + 1
+                } else {
+                    1
+                };
+                format!("{}. {}", number, text)
+            }
+            "to_do" => {
+                let mark = if data["checked"].as_bool().unwrap_or(false) {
+                    "x"
+                } else {
+                    " "
+                };
+                format!("- [{}] {}", mark, text)
+            }
+            "quote" => text
+                .lines()
+                .map(|l| format!("> {}", l))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            "code" => {
+                let language = match data["language"].as_str() {
+                    Some("plain text") | None => "",
+                    Some(l) => l,
+                };
+                let code = data["rich_text"]
+                    .as_array()
+                    .map_or(&[] as &[Value], |v| v)
+                    .iter()
+                    .filter_map(|t| t["text"]["content"].as_str().or(t["plain_text"].as_str()))
+                    .collect::<String>();
+                format!("```{}\n{}\n```", language, code)
+            }
+            "divider" => "---".to_string(),
+            _ => continue,
+        };
+
+        // list items of the same list are kept tight, everything else is separated by a blank line
+        if let Some(prev) = previous {
+            let tight = is_list_item(prev)
+                && is_list_item(kind)
+                && (prev == kind || !is_numbered(prev, kind));
+            out.push_str(if tight { "\n" } else { "\n\n" });
+        }
+
+        for (i, l) in line.split('\n').enumerate() {
+            if i > 0 {
+                out.push('\n');
+            }
+            if !l.is_empty() {
+                out.push_str(indent);
+            }
+            out.push_str(l);
+        }
+
+        if let Some(children) = data["children"].as_array() {
+            if !children.is_empty() {
+                let child_indent = match kind {
+                    "numbered_list_item" => format!("{}   ", indent),
+                    _ => format!("{}  ", indent),
+                };
+                out.push('\n');
+                write_blocks(out, children, &child_indent);
+            }
+        }
+
+        previous = Some(kind);
+    }
+}
+
+/// Whether a block type is rendered as a markdown list item
+fn is_list_item(kind: &str) -> bool {
+    return matches!(kind, "bulleted_list_item" | "numbered_list_item" | "to_do");
+}
+
+/// Whether exactly one of the two list item types is numbered
+fn is_numbered(a: &str, b: &str) -> bool {
+    return (a == "numbered_list_item") != (b == "numbered_list_item");
+}
+
+/// Inline formatting applied to a run of text
+#[derive(Default, Clone, PartialEq)]
+struct Annotations {
+    /// Bold text
+    bold: bool,
+    /// Italic text
+    italic: bool,
+    /// Struck through text
+    strikethrough: bool,
+    /// Inline code
+    code: bool,
+}
+
+/// Build a Notion rich text object
+fn rich_text(content: &str, annotations: &Annotations, link: Option<&str>) -> Value {
+    let link = link.map(|url| json!({ "url": url }));
+
+    return json!({
+        "type": "text",
+        "text": {
+            "content": content,
+            "link": link
+        },
+        "annotations": {
+            "bold": annotations.bold,
+            "italic": annotations.italic,
+            "strikethrough": annotations.strikethrough,
+            "underline": false,
+            "code": annotations.code,
+            "color": "default"
+        }
+    });
+}
+
+/// Build a Notion block with the given type and body
+/// Text runs longer than Notion accepts are split into several runs with the same formatting
+fn block(kind: &str, mut body: Value) -> Value {
+    if let Some(rich_text) = body.get_mut("rich_text").and_then(Value::as_array_mut) {
+        *rich_text = split_long_text(std::mem::take(rich_text));
+    }
+
+    return json!({
+        "object": "block",
+        "type": kind,
+        kind: body
+    });
+}
+
+/// Split rich text objects whose content is longer than MAX_TEXT_LENGTH
+/// Notion counts UTF-16 code units, so a chunk never ends in the middle of a character
+fn split_long_text(rich_text: Vec<Value>) -> Vec<Value> {
+    let mut out = Vec::with_capacity(rich_text.len());
+
+    for segment in rich_text {
+        let content = segment["text"]["content"].as_str().unwrap_or_default();
+        if content.encode_utf16().count() <= MAX_TEXT_LENGTH {
+            out.push(segment);
+            continue;
+        }
+
+        let mut chunk = String::new();
+        let mut length = 0;
+        for c in content.chars() {
+            if length + c.len_utf16() > MAX_TEXT_LENGTH {
+                let mut part = segment.clone();
+                part["text"]["content"] = Value::String(std::mem::take(&mut chunk));
+                out.push(part);
+                length = 0;
+            }
+            chunk.push(c);
+            length += c.len_utf16();
+        }
+
+        let mut part = segment;
+        part["text"]["content"] = Value::String(chunk);
+        out.push(part);
+    }
+
+    return out;
+}
+
+/// A block level element that is still being parsed
+enum Container {
+    /// The top level document
+    Root { children: Vec<Value> },
+    /// A list, its items are added to the enclosing container
+    List { ordered: bool },
+    /// A list item
+    Item {
+        ordered: bool,
+        checked: Option<bool>,
+        text: Option<Vec<Value>>,
+        children: Vec<Value>,
+    },
+    /// A block quote
+    Quote {
+        text: Vec<Value>,
+        children: Vec<Value>,
+    },
+}
+
+/// Consumes markdown events and turns them into Notion blocks
+struct BlockBuilder {
+    /// Open block level elements, the root is always at the bottom
+    stack: Vec<Container>,
+    /// Rich text collected for the current paragraph, heading or item
+    inline: Vec<Value>,
+    /// Current inline formatting
+    annotations: Annotations,
+    /// Target of the link currently being parsed
+    link: Option<String>,
+    /// Language and content of the code block being parsed
+    code: Option<(String, String)>,
+}
+
+impl BlockBuilder {
+    /// Create a builder with an empty document
+    fn new() -> BlockBuilder {
+        return BlockBuilder {
+            stack: vec![Container::Root {
+                children: Vec::new(),
+            }],
+            inline: Vec::new(),
+            annotations: Annotations::default(),
+            link: None,
+            code: None,
+        };
+    }
+
+    /// Return the blocks of the document
+    fn finish(mut self) -> Vec<Value> {
+        return match self.stack.swap_remove(0) {
+            Container::Root { children } => children,
+            _ => Vec::new(),
+        };
+    }
+
+    /// Process a single markdown event
+    fn handle(&mut self, event: Event) {
+        match event {
+            Event::Start(tag) => self.start(tag),
+            Event::End(tag) => self.end(tag),
+            Event::Text(text) => {
+                if let Some((_, code)) = &mut self.code {
+                    code.push_str(&text);
+                } else {
+                    self.push_text(&text, self.annotations.clone());
+                }
+            }
+            Event::Code(text) => {
+                let mut annotations = self.annotations.clone();
+                annotations.code = true;
+                self.push_text(&text, annotations);
+            }
+            Event::Html(text) => self.push_text(&text, self.annotations.clone()),
+            Event::SoftBreak | Event::HardBreak => self.push_text("\n", self.annotations.clone()),
+            Event::Rule => self.push_block(block("divider", json!({}))),
+            Event::TaskListMarker(checked) => {
+                if let Some(Container::Item { checked: c, .. }) = self.stack.last_mut() {
+                    *c = Some(checked);
+                }
+            }
+            Event::FootnoteReference(_) => {}
+        }
+    }
+
+    /// Handle the start of a tag
+    fn start(&mut self, tag: Tag) {
+        match tag {
+            Tag::List(start) => {
+                self.flush_item_text();
+                self.stack.push(Container::List {
+                    ordered: start.is_some(),
+                });
+            }
+            Tag::Item => {
+                let ordered = matches!(self.stack.last(), Some(Container::List { ordered: true }));
+                self.stack.push(Container::Item {
+                    ordered,
+                    checked: None,
+                    text: None,
+                    children: Vec::new(),
+                });
+            }
+            Tag::BlockQuote => {
+                self.flush_item_text();
+                self.stack.push(Container::Quote {
+                    text: Vec::new(),
+                    children: Vec::new(),
+                });
+            }
+            Tag::CodeBlock(kind) => {
+                self.flush_item_text();
+                let language = match kind {
+                    CodeBlockKind::Fenced(lang) if !lang.is_empty() => lang.to_string(),
+                    _ => "plain text".to_string(),
+                };
+                self.code = Some((language, String::new()));
+            }
+            Tag::Emphasis => self.annotations.italic = true,
+            Tag::Strong => self.annotations.bold = true,
+            Tag::Strikethrough => self.annotations.strikethrough = true,
+            Tag::Link(_, url, _) => self.link = Some(url.to_string()),
+            _ => {}
+        }
+    }
+
+    /// Handle the end of a tag
+    fn end(&mut self, tag: Tag) {
+        match tag {
+            Tag::Paragraph => {
+                let text = std::mem::take(&mut self.inline);
+                match self.stack.last_mut() {
+                    Some(Container::Item {
+                        text: item_text @ None,
+                        ..
+                    }) => *item_text = Some(text),
+                    Some(Container::Quote {
+                        text: quote,
+                        children,
+                    }) if children.is_empty() => {
+                        if !quote.is_empty() {
+                            quote.push(plain_rich_text("\n"));
+                        }
+                        quote.extend(text);
+                    }
+                    _ => self.push_block(block("paragraph", json!({ "rich_text": text }))),
+                }
+            }
+            Tag::Heading(level, _, _) => {
+                let kind = match level {
+                    HeadingLevel::H1 => "heading_1",
+                    HeadingLevel::H2 => "heading_2",
+                    _ => "heading_3",
+                };
+                let text = std::mem::take(&mut self.inline);
+                self.push_block(block(kind, json!({ "rich_text": text })));
+            }
+            Tag::CodeBlock(_) => {
+                if let Some((language, mut code)) = self.code.take() {
+                    if code.ends_with('\n') {
+                        code.pop();
+                    }
+                    let body = json!({
+                        "rich_text": [plain_rich_text(&code)],
+                        "language": language
+                    });
+                    self.push_block(block("code", body));
+                }
+            }
+            Tag::List(_) => {
+                self.stack.pop();
+            }
+            Tag::Item => {
+                self.flush_item_text();
+                if let Some(Container::Item {
+                    ordered,
+                    checked,
+                    text,
+                    children,
+                }) = self.stack.pop()
+                {
+                    let mut body = json!({ "rich_text": text.unwrap_or_default() });
+                    if !children.is_empty() {
+                        body["children"] = Value::Array(children);
+                    }
+                    let kind = match (checked, ordered) {
+                        (Some(checked), _) => {
+                            body["checked"] = Value::Bool(checked);
+                            "to_do"
+                        }
+                        (None, true) => "numbered_list_item",
+                        (None, false) => "bulleted_list_item",
+                    };
+                    self.push_block(block(kind, body));
+                }
+            }
+            Tag::BlockQuote => {
+                if let Some(Container::Quote { text, children }) = self.stack.pop() {
+                    let mut body = json!({ "rich_text": text });
+                    if !children.is_empty() {
+                        body["children"] = Value::Array(children);
+                    }
+                    self.push_block(block("quote", body));
+                }
+            }
+            Tag::Emphasis => self.annotations.italic = false,
+            Tag::Strong => self.annotations.bold = false,
+            Tag::Strikethrough => self.annotations.strikethrough = false,
+            Tag::Link(..) => self.link = None,
+            _ => {}
+        }
+    }
+
+    /// Append text to the current inline run, merging it with the previous segment when the formatting matches
+    fn push_text(&mut self, content: &str, annotations: Annotations) {
+        let segment = rich_text(content, &annotations, self.link.as_deref());
+
+        if let Some(last) = self.inline.last_mut() {
+            if last["annotations"] == segment["annotations"]
+                && last["text"]["link"] == segment["text"]["link"]
+            {
+                let merged = format!(
+                    "{}{}",
+                    last["text"]["content"].as_str().unwrap_or_default(),
+                    content
+                );
+                last["text"]["content"] = Value::String(merged);
+                return;
+            }
+        }
+
+        self.inline.push(segment);
+    }
+
+    /// Tight list items have no paragraph, so their text is taken when a nested block or the item ends
+    fn flush_item_text(&mut self) {
+        if let Some(Container::Item {
+            text: text @ None, ..
+        }) = self.stack.last_mut()
+        {
+            *text = Some(std::mem::take(&mut self.inline));
+        }
+    }
+
+    /// Add a finished block to the innermost container that can hold children
+    fn push_block(&mut self, block: Value) {
+        for container in self.stack.iter_mut().rev() {
+            match container {
+                Container::Root { children }
+                | Container::Item { children, .. }
+                | Container::Quote { children, .. } => {
+                    children.push(block);
+                    return;
+                }
+                Container::List { .. } => continue,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Check a markdown fixture against its block fixture in both directions
+    fn round_trip(markdown: &str, blocks: &str) {
+        let expected: Vec<Value> = serde_json::from_str(blocks).expect("invalid block fixture");
+
+        assert_eq!(markdown_to_blocks(markdown), expected);
+        assert_eq!(blocks_to_markdown(&expected), markdown.trim_end());
+    }
+
+    #[test]
+    fn annotations_round_trip() {
+        round_trip(
+            include_str!("../tests/fixtures/markdown/annotations.md"),
+            include_str!("../tests/fixtures/markdown/annotations.json"),
+        );
+    }
+
+    #[test]
+    fn nested_lists_round_trip() {
+        round_trip(
+            include_str!("../tests/fixtures/markdown/lists.md"),
+            include_str!("../tests/fixtures/markdown/lists.json"),
+        );
+    }
+
+    #[test]
+    fn long_text_is_split() {
+        let text = format!("{}🐈", "a".repeat(MAX_TEXT_LENGTH - 1));
+        let blocks = markdown_to_blocks(&text);
+        let runs = blocks[0]["paragraph"]["rich_text"].as_array().unwrap();
+
+        // the cat takes two UTF-16 code units, so it moves to the next run whole
+        assert_eq!(runs.len(), 2);
+        assert_eq!(
+            runs[0]["text"]["content"].as_str().unwrap(),
+            "a".repeat(MAX_TEXT_LENGTH - 1)
+        );
+        assert_eq!(runs[1]["text"]["content"], "🐈");
+        assert_eq!(blocks_to_markdown(&blocks), text);
+    }
+
+    #[test]
+    fn blocks_round_trip() {
+        round_trip(
+            include_str!("../tests/fixtures/markdown/blocks.md"),
+            include_str!("../tests/fixtures/markdown/blocks.json"),
+        );
+    }
+}
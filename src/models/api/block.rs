@@ -0,0 +1,338 @@
+use serde::{Deserialize, Serialize};
+
+use super::{rich_text::RichText, schema::EmptyObject};
+
+/// A notion block object, the content of a page is a list of blocks
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct Block {
+    /// The ID of the block, not set on blocks that are about to be created
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// Whether the block has nested blocks, which are fetched separately
+    #[serde(default)]
+    pub has_children: bool,
+    /// Whether the block has been archived
+    #[serde(default)]
+    pub archived: bool,
+    /// The block content, tagged by its type
+    #[serde(flatten)]
+    pub content: BlockContent,
+}
+
+/// The content of a block, tagged by its type
+/// Block types the app doesn't know about are read as Unsupported
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BlockContent {
+    /// A paragraph of text
+    Paragraph {
+        /// The text
+        paragraph: TextBlock,
+    },
+    /// A top level heading
+    #[serde(rename = "heading_1")]
+    Heading1 {
+        /// The heading text
+        heading_1: HeadingBlock,
+    },
+    /// A second level heading
+    #[serde(rename = "heading_2")]
+    Heading2 {
+        /// The heading text
+        heading_2: HeadingBlock,
+    },
+    /// A third level heading
+    #[serde(rename = "heading_3")]
+    Heading3 {
+        /// The heading text
+        heading_3: HeadingBlock,
+    },
+    /// An item of a bulleted list
+    BulletedListItem {
+        /// The item text
+        bulleted_list_item: TextBlock,
+    },
+    /// An item of a numbered list
+    NumberedListItem {
+        /// The item text
+        numbered_list_item: TextBlock,
+    },
+    /// A checklist item
+    ToDo {
+        /// The item text and whether it's checked
+        to_do: ToDoBlock,
+    },
+    /// A toggle that hides its children
+    Toggle {
+        /// The toggle text
+        toggle: TextBlock,
+    },
+    /// A quote
+    Quote {
+        /// The quoted text
+        quote: TextBlock,
+    },
+    /// A callout with an icon
+    Callout {
+        /// The callout text and icon
+        callout: CalloutBlock,
+    },
+    /// A code block
+    Code {
+        /// The code and its language
+        code: CodeBlock,
+    },
+    /// A KaTeX equation on its own line
+    Equation {
+        /// The equation
+        equation: EquationBlock,
+    },
+    /// A horizontal rule
+    Divider {
+        /// Has no content
+        #[serde(default)]
+        divider: EmptyObject,
+    },
+    /// A bookmarked link
+    Bookmark {
+        /// The link
+        bookmark: LinkBlock,
+    },
+    /// An embedded page
+    Embed {
+        /// The link
+        embed: LinkBlock,
+    },
+    /// An image
+    Image {
+        /// The image file
+        image: FileBlock,
+    },
+    /// A video
+    Video {
+        /// The video file
+        video: FileBlock,
+    },
+    /// A file
+    File {
+        /// The file
+        file: FileBlock,
+    },
+    /// A PDF
+    Pdf {
+        /// The PDF file
+        pdf: FileBlock,
+    },
+    /// A page nested in the page
+    ChildPage {
+        /// The title of the page
+        child_page: TitleBlock,
+    },
+    /// A database nested in the page
+    ChildDatabase {
+        /// The title of the database
+        child_database: TitleBlock,
+    },
+    /// A table of contents
+    TableOfContents {
+        /// The colour of the table of contents
+        #[serde(default)]
+        table_of_contents: ColorBlock,
+    },
+    /// A table, its rows are its children
+    Table {
+        /// The table layout
+        table: TableBlock,
+    },
+    /// A row of a table
+    TableRow {
+        /// The row's cells
+        table_row: TableRowBlock,
+    },
+    /// A block type the app doesn't read
+    #[serde(other)]
+    Unsupported,
+}
+
+/// Text content shared by paragraphs, list items, toggles and quotes
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct TextBlock {
+    /// The text
+    pub rich_text: Vec<RichText>,
+    /// Text or background colour
+    #[serde(default)]
+    pub color: String,
+    /// Nested blocks, only set when creating a block
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<Block>,
+}
+
+/// Heading content
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct HeadingBlock {
+    /// The heading text
+    pub rich_text: Vec<RichText>,
+    /// Text or background colour
+    #[serde(default)]
+    pub color: String,
+    /// Whether the heading hides its children like a toggle
+    #[serde(default)]
+    pub is_toggleable: bool,
+}
+
+/// Checklist item content
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct ToDoBlock {
+    /// The item text
+    pub rich_text: Vec<RichText>,
+    /// Whether the item is checked
+    #[serde(default)]
+    pub checked: bool,
+    /// Text or background colour
+    #[serde(default)]
+    pub color: String,
+    /// Nested blocks, only set when creating a block
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<Block>,
+}
+
+/// Callout content
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct CalloutBlock {
+    /// The callout text
+    pub rich_text: Vec<RichText>,
+    /// The icon shown before the text
+    #[serde(default)]
+    pub icon: Option<Icon>,
+    /// Text or background colour
+    #[serde(default)]
+    pub color: String,
+}
+
+/// Code block content
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct CodeBlock {
+    /// The code
+    pub rich_text: Vec<RichText>,
+    /// Caption shown under the code
+    #[serde(default)]
+    pub caption: Vec<RichText>,
+    /// The language the code is highlighted as, e.g. rust or plain text
+    pub language: String,
+}
+
+/// Block equation content
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct EquationBlock {
+    /// The KaTeX expression
+    pub expression: String,
+}
+
+/// Bookmark and embed content
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct LinkBlock {
+    /// The linked URL
+    pub url: String,
+    /// Caption shown under the link
+    #[serde(default)]
+    pub caption: Vec<RichText>,
+}
+
+/// Image, video, file and PDF content
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct FileBlock {
+    /// Caption shown under the file
+    #[serde(default)]
+    pub caption: Vec<RichText>,
+    /// Where the file is stored
+    #[serde(flatten)]
+    pub source: FileSource,
+}
+
+/// Where a file is stored, tagged by its type
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FileSource {
+    /// Hosted somewhere else
+    External {
+        /// The file's location
+        external: ExternalFile,
+    },
+    /// Uploaded to Notion
+    File {
+        /// The file's temporary location
+        file: HostedFile,
+    },
+}
+
+/// A file hosted outside Notion
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct ExternalFile {
+    /// Where the file is
+    pub url: String,
+}
+
+/// A file uploaded to Notion
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct HostedFile {
+    /// A signed link to the file
+    pub url: String,
+    /// When the link stops working
+    pub expiry_time: String,
+}
+
+/// A page or callout icon, tagged by its type
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Icon {
+    /// An emoji
+    Emoji {
+        /// The emoji
+        emoji: String,
+    },
+    /// An image hosted outside Notion
+    External {
+        /// The image's location
+        external: ExternalFile,
+    },
+    /// An image uploaded to Notion
+    File {
+        /// The image's temporary location
+        file: HostedFile,
+    },
+}
+
+/// Child page and child database content
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct TitleBlock {
+    /// The title of the page or database
+    pub title: String,
+}
+
+/// Content of blocks that only have a colour
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct ColorBlock {
+    /// Text or background colour
+    #[serde(default)]
+    pub color: String,
+}
+
+/// Table layout
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct TableBlock {
+    /// The number of columns
+    pub table_width: usize,
+    /// Whether the first row is a header
+    #[serde(default)]
+    pub has_column_header: bool,
+    /// Whether the first column is a header
+    #[serde(default)]
+    pub has_row_header: bool,
+}
+
+/// The cells of a table row
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct TableRowBlock {
+    /// The text of each cell, left to right
+    pub cells: Vec<Vec<RichText>>,
+}
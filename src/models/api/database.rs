@@ -0,0 +1,50 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    page::Parent,
+    rich_text::{self, RichText},
+    schema::Schema,
+};
+
+/// A notion database object
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct DatabaseObject {
+    /// The ID of the database
+    pub id: String,
+    /// When the database was created
+    pub created_time: DateTime<Utc>,
+    /// When the database was last edited
+    pub last_edited_time: DateTime<Utc>,
+    /// Database title, empty for untitled databases
+    #[serde(default)]
+    pub title: Vec<RichText>,
+    /// Database description
+    #[serde(default)]
+    pub description: Vec<RichText>,
+    /// What the database lives in
+    pub parent: Parent,
+    /// Link to the database in Notion
+    #[serde(default)]
+    pub url: String,
+    /// Whether the database has been archived
+    #[serde(default)]
+    pub archived: bool,
+    /// Whether the database is shown inline in its parent page
+    #[serde(default)]
+    pub is_inline: bool,
+    /// Database property schema
+    pub properties: Schema,
+}
+
+impl DatabaseObject {
+    /// The title as shown in Notion
+    pub fn plain_title(&self) -> String {
+        let title = rich_text::plain_text(&self.title);
+        if title.is_empty() {
+            return "Untitled".to_string();
+        }
+
+        return title;
+    }
+}
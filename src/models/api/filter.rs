@@ -0,0 +1,223 @@
+use serde::{Deserialize, Serialize};
+
+use super::schema::EmptyObject;
+
+/// A database query filter
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Filter {
+    /// Pages matching every filter
+    And {
+        /// The filters
+        and: Vec<Filter>,
+    },
+    /// Pages matching any filter
+    Or {
+        /// The filters
+        or: Vec<Filter>,
+    },
+    /// Pages whose creation or last edit time matches
+    Timestamp(TimestampFilter),
+    /// Pages whose property matches
+    Property {
+        /// The name or ID of the property
+        property: String,
+        /// The condition, keyed by the property type
+        #[serde(flatten)]
+        condition: PropertyCondition,
+    },
+}
+
+/// A filter on when pages were created or last edited, tagged by the timestamp
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "timestamp", rename_all = "snake_case")]
+pub enum TimestampFilter {
+    /// Filter on the creation time
+    CreatedTime {
+        /// The condition
+        created_time: DateCondition,
+    },
+    /// Filter on the last edit time
+    LastEditedTime {
+        /// The condition
+        last_edited_time: DateCondition,
+    },
+}
+
+/// A condition on a property, keyed by the property type
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PropertyCondition {
+    /// A condition on a title property
+    Title(TextCondition),
+    /// A condition on a rich text property
+    RichText(TextCondition),
+    /// A condition on a URL property
+    Url(TextCondition),
+    /// A condition on an email property
+    Email(TextCondition),
+    /// A condition on a phone number property
+    PhoneNumber(TextCondition),
+    /// A condition on a number property
+    Number(NumberCondition),
+    /// A condition on a checkbox property
+    Checkbox(CheckboxCondition),
+    /// A condition on a select property
+    Select(SelectCondition),
+    /// A condition on a status property
+    Status(SelectCondition),
+    /// A condition on a multi-select property
+    MultiSelect(ContainsCondition),
+    /// A condition on a date property
+    Date(DateCondition),
+    /// A condition on a people property, by user ID
+    People(ContainsCondition),
+    /// A condition on a created by property, by user ID
+    CreatedBy(ContainsCondition),
+    /// A condition on a last edited by property, by user ID
+    LastEditedBy(ContainsCondition),
+    /// A condition on a relation property, by page ID
+    Relation(ContainsCondition),
+    /// A condition on a files property
+    Files(EmptinessCondition),
+    /// A condition on the result of a formula
+    Formula(FormulaCondition),
+}
+
+/// A condition on text
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TextCondition {
+    /// The text is exactly this
+    Equals(String),
+    /// The text is anything but this
+    DoesNotEqual(String),
+    /// The text contains this
+    Contains(String),
+    /// The text doesn't contain this
+    DoesNotContain(String),
+    /// The text starts with this
+    StartsWith(String),
+    /// The text ends with this
+    EndsWith(String),
+    /// The text is empty, always true
+    IsEmpty(bool),
+    /// The text isn't empty, always true
+    IsNotEmpty(bool),
+}
+
+/// A condition on a number
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum NumberCondition {
+    /// The number is exactly this
+    Equals(f64),
+    /// The number is anything but this
+    DoesNotEqual(f64),
+    /// The number is greater than this
+    GreaterThan(f64),
+    /// The number is less than this
+    LessThan(f64),
+    /// The number is at least this
+    GreaterThanOrEqualTo(f64),
+    /// The number is at most this
+    LessThanOrEqualTo(f64),
+    /// The number isn't set, always true
+    IsEmpty(bool),
+    /// The number is set, always true
+    IsNotEmpty(bool),
+}
+
+/// A condition on a checkbox
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckboxCondition {
+    /// The box is checked if true
+    Equals(bool),
+    /// The box is unchecked if true
+    DoesNotEqual(bool),
+}
+
+/// A condition on a select or status option
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SelectCondition {
+    /// The option is this one
+    Equals(String),
+    /// The option is any but this one
+    DoesNotEqual(String),
+    /// No option is set, always true
+    IsEmpty(bool),
+    /// An option is set, always true
+    IsNotEmpty(bool),
+}
+
+/// A condition on a list of values, e.g. multi-select options or people
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ContainsCondition {
+    /// The list contains this value
+    Contains(String),
+    /// The list doesn't contain this value
+    DoesNotContain(String),
+    /// The list is empty, always true
+    IsEmpty(bool),
+    /// The list isn't empty, always true
+    IsNotEmpty(bool),
+}
+
+/// A condition on whether a value is set
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EmptinessCondition {
+    /// The value isn't set, always true
+    IsEmpty(bool),
+    /// The value is set, always true
+    IsNotEmpty(bool),
+}
+
+/// A condition on a date, dates are ISO 8601 dates or timestamps
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DateCondition {
+    /// The date is this date
+    Equals(String),
+    /// The date is before this date
+    Before(String),
+    /// The date is after this date
+    After(String),
+    /// The date is this date or before it
+    OnOrBefore(String),
+    /// The date is this date or after it
+    OnOrAfter(String),
+    /// The date isn't set, always true
+    IsEmpty(bool),
+    /// The date is set, always true
+    IsNotEmpty(bool),
+    /// The date is in the past week
+    PastWeek(EmptyObject),
+    /// The date is in the past month
+    PastMonth(EmptyObject),
+    /// The date is in the past year
+    PastYear(EmptyObject),
+    /// The date is in the coming week
+    NextWeek(EmptyObject),
+    /// The date is in the coming month
+    NextMonth(EmptyObject),
+    /// The date is in the coming year
+    NextYear(EmptyObject),
+}
+
+/// A condition on the result of a formula, keyed by the result type
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FormulaCondition {
+    /// A condition on a text result
+    String(TextCondition),
+    /// A condition on a checkbox result
+    Checkbox(CheckboxCondition),
+    /// A condition on a number result
+    Number(NumberCondition),
+    /// A condition on a date result
+    Date(DateCondition),
+}
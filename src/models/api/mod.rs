@@ -0,0 +1,148 @@
+/// Page content blocks
+pub mod block;
+/// Database objects
+pub mod database;
+/// Database query filters
+pub mod filter;
+/// Page objects
+pub mod page;
+/// Page property values
+pub mod property;
+/// Formatted text
+pub mod rich_text;
+/// Database property definitions
+pub mod schema;
+/// Database query sorts
+pub mod sort;
+/// Users and bots
+pub mod user;
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Debug;
+
+    use chrono::DateTime;
+    use serde::{de::DeserializeOwned, Serialize};
+    use serde_json::Value;
+
+    use super::{
+        block::Block, database::DatabaseObject, filter::Filter, page::Page, rich_text,
+        rich_text::RichText, sort::Sort, user::User,
+    };
+
+    /// Parse a fixture, check that writing it back parses to the same value
+    /// and that everything written matches what Notion sent
+    fn round_trip<T: DeserializeOwned + Serialize + PartialEq + Debug>(fixture: &Value) -> T {
+        let parsed: T = serde_json::from_value(fixture.clone()).expect("fixture doesn't parse");
+        let written = serde_json::to_value(&parsed).expect("can't serialize");
+
+        assert_subset(&written, fixture, "$");
+        assert_eq!(serde_json::from_value::<T>(written).unwrap(), parsed);
+
+        return parsed;
+    }
+
+    /// Check that every value written is in the fixture, keys the models don't read may be missing
+    /// Missing keys match null, numbers compare as floats and timestamps compare as instants
+    fn assert_subset(written: &Value, fixture: &Value, path: &str) {
+        match (written, fixture) {
+            (Value::Object(written), Value::Object(fixture)) => {
+                for (key, value) in written {
+                    let path = format!("{}.{}", path, key);
+                    match fixture.get(key) {
+                        Some(expected) => assert_subset(value, expected, &path),
+                        None => assert!(value.is_null(), "{} isn't in the fixture", path),
+                    }
+                }
+            }
+            (Value::Array(written), Value::Array(fixture)) => {
+                assert_eq!(
+                    written.len(),
+                    fixture.len(),
+                    "{} has a different length",
+                    path
+                );
+                for (i, (value, expected)) in written.iter().zip(fixture).enumerate() {
+                    assert_subset(value, expected, &format!("{}[{}]", path, i));
+                }
+            }
+            (Value::Number(written), Value::Number(fixture)) => {
+                assert_eq!(written.as_f64(), fixture.as_f64(), "{} differs", path);
+            }
+            (Value::String(written), Value::String(fixture)) if written != fixture => {
+                let written = DateTime::parse_from_rfc3339(written);
+                let fixture = DateTime::parse_from_rfc3339(fixture);
+                assert!(
+                    matches!((written, fixture), (Ok(w), Ok(f)) if w == f),
+                    "{} differs",
+                    path
+                );
+            }
+            _ => assert_eq!(written, fixture, "{} differs", path),
+        }
+    }
+
+    /// Load a fixture from tests/fixtures/notion
+    fn fixture(json: &str) -> Value {
+        return serde_json::from_str(json).expect("invalid fixture");
+    }
+
+    #[test]
+    fn page_round_trip() {
+        let page: Page = round_trip(&fixture(include_str!(
+            "../../../tests/fixtures/notion/page.json"
+        )));
+
+        assert_eq!(page.properties["Name"].plain_text(), "Finish civ6 game");
+        assert_eq!(page.properties["Task ID"].plain_text(), "TASK-42");
+    }
+
+    #[test]
+    fn database_round_trip() {
+        let database: DatabaseObject = round_trip(&fixture(include_str!(
+            "../../../tests/fixtures/notion/database.json"
+        )));
+
+        assert_eq!(database.plain_title(), "Task List");
+        assert_eq!(
+            database.properties["Stage"].option_names(),
+            vec!["Not started", "In progress", "Done"]
+        );
+    }
+
+    #[test]
+    fn users_round_trip() {
+        round_trip::<Vec<User>>(&fixture(include_str!(
+            "../../../tests/fixtures/notion/users.json"
+        )));
+    }
+
+    #[test]
+    fn rich_text_round_trip() {
+        let text: Vec<RichText> = round_trip(&fixture(include_str!(
+            "../../../tests/fixtures/notion/rich_text.json"
+        )));
+
+        assert!(rich_text::plain_text(&text).starts_with("Read the manual before 2022-10-08"));
+    }
+
+    #[test]
+    fn blocks_round_trip() {
+        let list = fixture(include_str!("../../../tests/fixtures/notion/blocks.json"));
+        round_trip::<Vec<Block>>(&list["results"]);
+    }
+
+    #[test]
+    fn filters_round_trip() {
+        round_trip::<Vec<Filter>>(&fixture(include_str!(
+            "../../../tests/fixtures/notion/filters.json"
+        )));
+    }
+
+    #[test]
+    fn sorts_round_trip() {
+        round_trip::<Vec<Sort>>(&fixture(include_str!(
+            "../../../tests/fixtures/notion/sorts.json"
+        )));
+    }
+}
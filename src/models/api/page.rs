@@ -0,0 +1,64 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{property::PropertyValue, user::PartialUser};
+
+/// A notion page object
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct Page {
+    /// The ID of the page
+    pub id: String,
+    /// When the page was created
+    pub created_time: DateTime<Utc>,
+    /// Who created the page
+    pub created_by: PartialUser,
+    /// When the page was last edited
+    pub last_edited_time: DateTime<Utc>,
+    /// Who last edited the page
+    pub last_edited_by: PartialUser,
+    /// Whether the page has been archived
+    #[serde(default)]
+    pub archived: bool,
+    /// What the page lives in
+    pub parent: Parent,
+    /// Link to the page in Notion
+    #[serde(default)]
+    pub url: String,
+    /// Page property values by property name
+    pub properties: BTreeMap<String, PropertyValue>,
+}
+
+/// The parent of a page or database, tagged by its type
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Parent {
+    /// A row of a database
+    DatabaseId {
+        /// The ID of the database
+        database_id: String,
+    },
+    /// A child of a page
+    PageId {
+        /// The ID of the page
+        page_id: String,
+    },
+    /// A child of a block
+    BlockId {
+        /// The ID of the block
+        block_id: String,
+    },
+    /// At the top level of the workspace
+    Workspace {
+        /// Always true
+        workspace: bool,
+    },
+}
+
+/// A reference to a page or database by ID, as in relations and mentions
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct PageReference {
+    /// The ID of the page
+    pub id: String,
+}
@@ -0,0 +1,304 @@
+use serde::{Deserialize, Serialize};
+
+use super::{
+    page::PageReference,
+    rich_text::{self, RichText},
+    user::PartialUser,
+};
+
+/// The value of a page property, tagged by its type
+/// Property types the app doesn't know about are read as Unsupported instead of failing the page
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PropertyValue {
+    /// The page title
+    Title {
+        /// The title text
+        title: Vec<RichText>,
+    },
+    /// Formatted text
+    RichText {
+        /// The text
+        rich_text: Vec<RichText>,
+    },
+    /// A number
+    Number {
+        /// The number, empty when not set
+        number: Option<f64>,
+    },
+    /// One option of a select
+    Select {
+        /// The chosen option, empty when not set
+        select: Option<SelectOption>,
+    },
+    /// One option of a status
+    Status {
+        /// The chosen option, empty when not set
+        status: Option<SelectOption>,
+    },
+    /// Any number of options of a multi-select
+    MultiSelect {
+        /// The chosen options
+        multi_select: Vec<SelectOption>,
+    },
+    /// A date or date range
+    Date {
+        /// The date, empty when not set
+        date: Option<DateValue>,
+    },
+    /// Mentioned users
+    People {
+        /// The users
+        people: Vec<PartialUser>,
+    },
+    /// Uploaded or linked files
+    Files {
+        /// The files
+        files: Vec<FileValue>,
+    },
+    /// A checkbox
+    Checkbox {
+        /// Whether the box is checked
+        checkbox: bool,
+    },
+    /// A URL
+    Url {
+        /// The URL, empty when not set
+        url: Option<String>,
+    },
+    /// An email address
+    Email {
+        /// The address, empty when not set
+        email: Option<String>,
+    },
+    /// A phone number
+    PhoneNumber {
+        /// The number, empty when not set
+        phone_number: Option<String>,
+    },
+    /// The result of a formula
+    Formula {
+        /// The result
+        formula: FormulaValue,
+    },
+    /// Related pages
+    Relation {
+        /// The related pages
+        relation: Vec<PageReference>,
+    },
+    /// Values rolled up from related pages
+    Rollup {
+        /// The rolled up value
+        rollup: RollupValue,
+    },
+    /// When the page was created
+    CreatedTime {
+        /// ISO 8601 timestamp
+        created_time: String,
+    },
+    /// Who created the page
+    CreatedBy {
+        /// The user
+        created_by: PartialUser,
+    },
+    /// When the page was last edited
+    LastEditedTime {
+        /// ISO 8601 timestamp
+        last_edited_time: String,
+    },
+    /// Who last edited the page
+    LastEditedBy {
+        /// The user
+        last_edited_by: PartialUser,
+    },
+    /// An auto incrementing ID
+    UniqueId {
+        /// The ID
+        unique_id: UniqueId,
+    },
+    /// A property type the app doesn't read
+    #[serde(other)]
+    Unsupported,
+}
+
+/// A select, multi-select or status option
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct SelectOption {
+    /// The ID of the option, not needed when choosing an option by name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// The option name
+    pub name: String,
+    /// The option colour, e.g. red
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+}
+
+/// A date, or a date range if end is set
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct DateValue {
+    /// Start date, with a time if one was given
+    pub start: String,
+    /// End date of a range
+    #[serde(default)]
+    pub end: Option<String>,
+    /// Time zone the times are in, empty for UTC offsets in the times themselves
+    #[serde(default)]
+    pub time_zone: Option<String>,
+}
+
+/// A file attached to a page
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct FileValue {
+    /// The file name
+    pub name: String,
+}
+
+/// An auto incrementing ID, with an optional prefix
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct UniqueId {
+    /// The number, empty until Notion assigns one
+    pub number: Option<u64>,
+    /// Prefix shown before the number
+    #[serde(default)]
+    pub prefix: Option<String>,
+}
+
+/// The result of a formula, tagged by its type
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FormulaValue {
+    /// Text result
+    String {
+        /// The text
+        string: Option<String>,
+    },
+    /// Number result
+    Number {
+        /// The number
+        number: Option<f64>,
+    },
+    /// Checkbox result
+    Boolean {
+        /// Whether the result is true
+        boolean: Option<bool>,
+    },
+    /// Date result
+    Date {
+        /// The date
+        date: Option<DateValue>,
+    },
+}
+
+/// The result of a rollup, tagged by its type
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RollupValue {
+    /// A calculated number, e.g. a sum
+    Number {
+        /// The number
+        number: Option<f64>,
+        /// The rollup function, e.g. sum
+        #[serde(default)]
+        function: String,
+    },
+    /// A calculated date, e.g. the latest date
+    Date {
+        /// The date
+        date: Option<DateValue>,
+        /// The rollup function, e.g. latest_date
+        #[serde(default)]
+        function: String,
+    },
+    /// The rolled up property values themselves
+    Array {
+        /// The values
+        array: Vec<PropertyValue>,
+        /// The rollup function, e.g. show_original
+        #[serde(default)]
+        function: String,
+    },
+    /// A rollup result the app doesn't read
+    #[serde(other)]
+    Unsupported,
+}
+
+impl PropertyValue {
+    /// Render the value as plain text, lists of values are joined with ", "
+    pub fn plain_text(&self) -> String {
+        let join = |values: Vec<String>| values.join(", ");
+
+        return match self {
+            PropertyValue::Title { title: text } | PropertyValue::RichText { rich_text: text } => {
+                rich_text::plain_text(text)
+            }
+            PropertyValue::Number { number } => number_text(number),
+            PropertyValue::Select { select: option } | PropertyValue::Status { status: option } => {
+                option.as_ref().map(|o| o.name.clone()).unwrap_or_default()
+            }
+            PropertyValue::MultiSelect { multi_select } => {
+                join(multi_select.iter().map(|o| o.name.clone()).collect())
+            }
+            PropertyValue::Date { date } => date_text(date),
+            PropertyValue::People { people } => {
+                join(people.iter().map(PartialUser::display_name).collect())
+            }
+            PropertyValue::Files { files } => join(files.iter().map(|f| f.name.clone()).collect()),
+            PropertyValue::Checkbox { checkbox } => checkbox.to_string(),
+            PropertyValue::Url { url: text }
+            | PropertyValue::Email { email: text }
+            | PropertyValue::PhoneNumber { phone_number: text } => text.clone().unwrap_or_default(),
+            PropertyValue::Formula { formula } => match formula {
+                FormulaValue::String { string } => string.clone().unwrap_or_default(),
+                FormulaValue::Number { number } => number_text(number),
+                FormulaValue::Boolean { boolean } => {
+                    boolean.map(|b| b.to_string()).unwrap_or_default()
+                }
+                FormulaValue::Date { date } => date_text(date),
+            },
+            PropertyValue::Relation { relation } => {
+                join(relation.iter().map(|r| r.id.clone()).collect())
+            }
+            PropertyValue::Rollup { rollup } => match rollup {
+                RollupValue::Number { number, .. } => number_text(number),
+                RollupValue::Date { date, .. } => date_text(date),
+                RollupValue::Array { array, .. } => {
+                    join(array.iter().map(PropertyValue::plain_text).collect())
+                }
+                RollupValue::Unsupported => String::new(),
+            },
+            PropertyValue::CreatedTime { created_time: time }
+            | PropertyValue::LastEditedTime {
+                last_edited_time: time,
+            } => time.clone(),
+            PropertyValue::CreatedBy { created_by: by }
+            | PropertyValue::LastEditedBy { last_edited_by: by } => by.display_name(),
+            PropertyValue::UniqueId { unique_id } => {
+                let number = unique_id.number.map(|n| n.to_string()).unwrap_or_default();
+                match &unique_id.prefix {
+                    Some(prefix) => format!("{}-{}", prefix, number),
+                    None => number,
+                }
+            }
+            PropertyValue::Unsupported => String::new(),
+        };
+    }
+}
+
+/// A number as text, empty if not set
+fn number_text(number: &Option<f64>) -> String {
+    return number.map(|n| n.to_string()).unwrap_or_default();
+}
+
+/// A date as text, ranges are written start/end
+fn date_text(date: &Option<DateValue>) -> String {
+    return match date {
+        Some(DateValue {
+            start,
+            end: Some(end),
+            ..
+        }) => format!("{}/{}", start, end),
+        Some(DateValue { start, .. }) => start.clone(),
+        None => String::new(),
+    };
+}
@@ -0,0 +1,122 @@
+use serde::{Deserialize, Serialize};
+
+use super::{page::PageReference, property::DateValue, user::PartialUser};
+
+/// A rich text object
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct RichText {
+    /// What the text is made of, tagged by its type
+    #[serde(flatten)]
+    pub content: RichTextContent,
+    /// Formatting applied to the text
+    #[serde(default)]
+    pub annotations: Annotations,
+    /// The text without formatting
+    #[serde(default)]
+    pub plain_text: String,
+    /// Link the text points to
+    #[serde(default)]
+    pub href: Option<String>,
+}
+
+/// The content of a rich text object
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RichTextContent {
+    /// Plain text, optionally linked
+    Text {
+        /// The text and its link
+        text: Text,
+    },
+    /// A mention of a user, page, database or date
+    Mention {
+        /// What is mentioned
+        mention: Mention,
+    },
+    /// An inline KaTeX equation
+    Equation {
+        /// The equation
+        equation: Equation,
+    },
+}
+
+/// The text of a text rich text object
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Text {
+    /// The text itself
+    pub content: String,
+    /// Where the text links to
+    #[serde(default)]
+    pub link: Option<Link>,
+}
+
+/// A link from a piece of text
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Link {
+    /// The linked URL
+    pub url: String,
+}
+
+/// A KaTeX equation
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Equation {
+    /// The KaTeX expression
+    pub expression: String,
+}
+
+/// What a mention refers to, tagged by its type
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Mention {
+    /// A user
+    User {
+        /// The mentioned user
+        user: PartialUser,
+    },
+    /// A page
+    Page {
+        /// The mentioned page
+        page: PageReference,
+    },
+    /// A database
+    Database {
+        /// The mentioned database
+        database: PageReference,
+    },
+    /// A date or date range
+    Date {
+        /// The mentioned date
+        date: DateValue,
+    },
+    /// A link preview of a URL
+    LinkPreview {
+        /// The previewed link
+        link_preview: Link,
+    },
+    /// A mention type the app doesn't read
+    #[serde(other)]
+    Unsupported,
+}
+
+/// Formatting of a rich text object
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Default)]
+#[serde(default)]
+pub struct Annotations {
+    /// Whether the text is bold
+    pub bold: bool,
+    /// Whether the text is italic
+    pub italic: bool,
+    /// Whether the text is struck through
+    pub strikethrough: bool,
+    /// Whether the text is underlined
+    pub underline: bool,
+    /// Whether the text is inline code
+    pub code: bool,
+    /// Text or background colour, e.g. red or red_background
+    pub color: String,
+}
+
+/// Join the plain text of a list of rich text objects
+pub fn plain_text(texts: &[RichText]) -> String {
+    return texts.iter().map(|t| t.plain_text.as_str()).collect();
+}
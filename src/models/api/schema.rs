@@ -0,0 +1,271 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::property::SelectOption;
+
+/// A database's properties by name
+pub type Schema = BTreeMap<String, PropertySchema>;
+
+/// The definition of a database property, tagged by its type
+/// Property types the app doesn't know about are read as Unsupported instead of failing the database
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PropertySchema {
+    /// The title of each page
+    Title {
+        /// Has no settings
+        #[serde(default)]
+        title: EmptyObject,
+    },
+    /// Formatted text
+    RichText {
+        /// Has no settings
+        #[serde(default)]
+        rich_text: EmptyObject,
+    },
+    /// A number
+    Number {
+        /// How the number is displayed
+        #[serde(default)]
+        number: NumberSchema,
+    },
+    /// One option out of a list
+    Select {
+        /// The options
+        #[serde(default)]
+        select: OptionsSchema,
+    },
+    /// One status out of a list grouped into to-do, in progress and complete
+    Status {
+        /// The options and their groups
+        #[serde(default)]
+        status: StatusSchema,
+    },
+    /// Any number of options out of a list
+    MultiSelect {
+        /// The options
+        #[serde(default)]
+        multi_select: OptionsSchema,
+    },
+    /// A date or date range
+    Date {
+        /// Has no settings
+        #[serde(default)]
+        date: EmptyObject,
+    },
+    /// Users
+    People {
+        /// Has no settings
+        #[serde(default)]
+        people: EmptyObject,
+    },
+    /// Uploaded or linked files
+    Files {
+        /// Has no settings
+        #[serde(default)]
+        files: EmptyObject,
+    },
+    /// A checkbox
+    Checkbox {
+        /// Has no settings
+        #[serde(default)]
+        checkbox: EmptyObject,
+    },
+    /// A URL
+    Url {
+        /// Has no settings
+        #[serde(default)]
+        url: EmptyObject,
+    },
+    /// An email address
+    Email {
+        /// Has no settings
+        #[serde(default)]
+        email: EmptyObject,
+    },
+    /// A phone number
+    PhoneNumber {
+        /// Has no settings
+        #[serde(default)]
+        phone_number: EmptyObject,
+    },
+    /// A value computed from the page's other properties
+    Formula {
+        /// The formula
+        #[serde(default)]
+        formula: FormulaSchema,
+    },
+    /// Links to pages of a database
+    Relation {
+        /// The related database
+        #[serde(default)]
+        relation: RelationSchema,
+    },
+    /// Values of a property of related pages
+    Rollup {
+        /// Which property is rolled up and how
+        #[serde(default)]
+        rollup: RollupSchema,
+    },
+    /// When the page was created
+    CreatedTime {
+        /// Has no settings
+        #[serde(default)]
+        created_time: EmptyObject,
+    },
+    /// Who created the page
+    CreatedBy {
+        /// Has no settings
+        #[serde(default)]
+        created_by: EmptyObject,
+    },
+    /// When the page was last edited
+    LastEditedTime {
+        /// Has no settings
+        #[serde(default)]
+        last_edited_time: EmptyObject,
+    },
+    /// Who last edited the page
+    LastEditedBy {
+        /// Has no settings
+        #[serde(default)]
+        last_edited_by: EmptyObject,
+    },
+    /// An auto incrementing ID
+    UniqueId {
+        /// The ID prefix
+        #[serde(default)]
+        unique_id: UniqueIdSchema,
+    },
+    /// A property type the app doesn't read
+    #[serde(other)]
+    Unsupported,
+}
+
+/// Settings of property types that have none, written as {}
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct EmptyObject {}
+
+/// Settings of a number property
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct NumberSchema {
+    /// How the number is displayed, e.g. number, percent or dollar
+    #[serde(default)]
+    pub format: String,
+}
+
+/// The options of a select or multi-select property
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct OptionsSchema {
+    /// The options, in the order Notion shows them
+    #[serde(default)]
+    pub options: Vec<SelectOption>,
+}
+
+/// The options of a status property
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct StatusSchema {
+    /// The options, in the order Notion shows them
+    #[serde(default)]
+    pub options: Vec<SelectOption>,
+    /// The groups the options are sorted into
+    #[serde(default)]
+    pub groups: Vec<StatusGroup>,
+}
+
+/// A group of status options, e.g. Complete
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct StatusGroup {
+    /// The ID of the group
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// The group name
+    pub name: String,
+    /// The group colour
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    /// The IDs of the options in the group
+    #[serde(default)]
+    pub option_ids: Vec<String>,
+}
+
+/// Settings of a formula property
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct FormulaSchema {
+    /// The formula expression
+    #[serde(default)]
+    pub expression: String,
+}
+
+/// Settings of a relation property
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct RelationSchema {
+    /// The ID of the related database
+    #[serde(default)]
+    pub database_id: String,
+}
+
+/// Settings of a rollup property
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct RollupSchema {
+    /// The relation property the pages are found through
+    #[serde(default)]
+    pub relation_property_name: String,
+    /// The property of the related pages that is rolled up
+    #[serde(default)]
+    pub rollup_property_name: String,
+    /// How the values are combined, e.g. sum or show_original
+    #[serde(default)]
+    pub function: String,
+}
+
+/// Settings of a unique ID property
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct UniqueIdSchema {
+    /// Prefix shown before the number
+    #[serde(default)]
+    pub prefix: Option<String>,
+}
+
+impl PropertySchema {
+    /// The Notion name of the property type, e.g. multi_select
+    pub fn kind(&self) -> &'static str {
+        return match self {
+            PropertySchema::Title { .. } => "title",
+            PropertySchema::RichText { .. } => "rich_text",
+            PropertySchema::Number { .. } => "number",
+            PropertySchema::Select { .. } => "select",
+            PropertySchema::Status { .. } => "status",
+            PropertySchema::MultiSelect { .. } => "multi_select",
+            PropertySchema::Date { .. } => "date",
+            PropertySchema::People { .. } => "people",
+            PropertySchema::Files { .. } => "files",
+            PropertySchema::Checkbox { .. } => "checkbox",
+            PropertySchema::Url { .. } => "url",
+            PropertySchema::Email { .. } => "email",
+            PropertySchema::PhoneNumber { .. } => "phone_number",
+            PropertySchema::Formula { .. } => "formula",
+            PropertySchema::Relation { .. } => "relation",
+            PropertySchema::Rollup { .. } => "rollup",
+            PropertySchema::CreatedTime { .. } => "created_time",
+            PropertySchema::CreatedBy { .. } => "created_by",
+            PropertySchema::LastEditedTime { .. } => "last_edited_time",
+            PropertySchema::LastEditedBy { .. } => "last_edited_by",
+            PropertySchema::UniqueId { .. } => "unique_id",
+            PropertySchema::Unsupported => "unsupported",
+        };
+    }
+
+    /// The option names of a select, multi-select or status property, empty for other types
+    pub fn option_names(&self) -> Vec<&str> {
+        let options = match self {
+            PropertySchema::Select { select: o }
+            | PropertySchema::MultiSelect { multi_select: o } => &o.options,
+            PropertySchema::Status { status } => &status.options,
+            _ => return Vec::new(),
+        };
+
+        return options.iter().map(|o| o.name.as_str()).collect();
+    }
+}
@@ -0,0 +1,41 @@
+use serde::{Deserialize, Serialize};
+
+/// A database query sort, earlier sorts take precedence
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum Sort {
+    /// Sort by a property
+    Property {
+        /// The name or ID of the property
+        property: String,
+        /// Which way to sort
+        direction: Direction,
+    },
+    /// Sort by when pages were created or last edited
+    Timestamp {
+        /// Which timestamp to sort by
+        timestamp: Timestamp,
+        /// Which way to sort
+        direction: Direction,
+    },
+}
+
+/// Which way a sort goes
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    /// Smallest or earliest first
+    Ascending,
+    /// Largest or latest first
+    Descending,
+}
+
+/// A timestamp every page has
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Timestamp {
+    /// When the page was created
+    CreatedTime,
+    /// When the page was last edited
+    LastEditedTime,
+}
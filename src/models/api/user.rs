@@ -0,0 +1,85 @@
+use serde::{Deserialize, Serialize};
+
+/// A notion user object
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct User {
+    /// The ID of the user
+    pub id: String,
+    /// Display name, missing for some bots
+    #[serde(default)]
+    pub name: Option<String>,
+    /// URL of the user's avatar
+    #[serde(default)]
+    pub avatar_url: Option<String>,
+    /// Whether the user is a person or a bot
+    #[serde(flatten)]
+    pub kind: UserKind,
+}
+
+/// What kind of user a user object is, tagged by its type
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum UserKind {
+    /// A person in the workspace
+    Person {
+        /// Details of the person
+        person: Person,
+    },
+    /// An integration
+    Bot {
+        /// Details of the bot
+        bot: Bot,
+    },
+}
+
+/// Details of a person
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct Person {
+    /// Email address, only present if the integration can read email addresses
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+}
+
+/// Details of a bot, only present for the bot the token belongs to
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Default)]
+pub struct Bot {
+    /// Who owns the bot, a user or the workspace
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<BotOwner>,
+    /// Name of the workspace the bot belongs to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workspace_name: Option<String>,
+}
+
+/// The owner of a bot, tagged by its type
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BotOwner {
+    /// The whole workspace, for internal integrations
+    Workspace {
+        /// Always true
+        workspace: bool,
+    },
+    /// The user who authorised a public integration
+    User {
+        /// The owning user
+        user: PartialUser,
+    },
+}
+
+/// A user where only the ID is always populated, as in created_by
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct PartialUser {
+    /// The ID of the user
+    pub id: String,
+    /// Display name, missing if the integration can't read user information
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+impl PartialUser {
+    /// The name of the user, or its ID if the name isn't known
+    pub fn display_name(&self) -> String {
+        return self.name.clone().unwrap_or_else(|| self.id.clone());
+    }
+}
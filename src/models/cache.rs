@@ -0,0 +1,238 @@
+use std::collections::{BTreeMap, HashSet};
+
+use super::{api::schema::Schema, journal::Operation, task::Task, task_status::TaskStatus};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// The format cached tasks are written in, bumped when tasks gain fields older caches don't have
+/// Version 1 added the tasks' other properties
+pub const CACHE_VERSION: u32 = 1;
+
+/// Locally cached copies of databases, keyed by database ID
+#[derive(Deserialize, Serialize)]
+pub struct Cache {
+    /// The format the cache was written in, caches from before versioning are 0
+    #[serde(default)]
+    pub version: u32,
+    /// Cached databases
+    pub databases: BTreeMap<String, DatabaseCache>,
+}
+
+/// The cached tasks and schema of a single database
+#[derive(Deserialize, Serialize, Default)]
+pub struct DatabaseCache {
+    /// The database's property schema as returned by Notion
+    pub schema: Option<Schema>,
+    /// Tasks keyed by ID
+    pub tasks: BTreeMap<String, Task>,
+    /// When the tasks were last fetched in full
+    pub refreshed_at: Option<DateTime<Utc>>,
+    /// Tasks edited on or after this time haven't been pulled yet
+    #[serde(default)]
+    pub sync_cursor: Option<DateTime<Utc>>,
+}
+
+impl DatabaseCache {
+    /// Store a task unless the cached copy was edited more recently
+    pub fn upsert(&mut self, task: Task) {
+        let newer = self
+            .tasks
+            .get(&task.id)
+            .is_none_or(|cached| cached.last_edited_time <= task.last_edited_time);
+
+        if newer {
+            self.tasks.insert(task.id.clone(), task);
+        }
+    }
+
+    /// Merge changed tasks into the cache, archived tasks are dropped
+    /// Returns how many tasks were updated and how many were removed
+    pub fn merge(&mut self, tasks: Vec<Task>) -> (usize, usize) {
+        let (mut updated, mut removed) = (0, 0);
+
+        for task in tasks {
+            if task.archived {
+                removed += usize::from(self.tasks.remove(&task.id).is_some());
+            } else {
+                updated += 1;
+                self.upsert(task);
+            }
+        }
+
+        return (updated, removed);
+    }
+
+    /// Drop the cached tasks with these IDs
+    /// Returns how many tasks were removed
+    pub fn remove(&mut self, ids: &[String]) -> usize {
+        return ids
+            .iter()
+            .filter(|id| self.tasks.remove(*id).is_some())
+            .count();
+    }
+
+    /// Drop the cached tasks whose ID isn't listed
+    /// Returns how many tasks were removed
+    pub fn retain_listed(&mut self, listed: &HashSet<String>) -> usize {
+        let before = self.tasks.len();
+        self.tasks.retain(|id, _| listed.contains(id));
+
+        return before - self.tasks.len();
+    }
+}
+
+impl Default for Cache {
+    fn default() -> Self {
+        return Cache {
+            version: CACHE_VERSION,
+            databases: BTreeMap::new(),
+        };
+    }
+}
+
+impl Cache {
+    /// Make every database of a cache written in an older format pull all its tasks again,
+    /// since incremental pulls only refetch edited tasks and the rest would keep missing fields
+    /// The cached tasks are kept so they can still be read offline until then
+    pub fn upgrade(&mut self) {
+        if self.version >= CACHE_VERSION {
+            return;
+        }
+
+        for db in self.databases.values_mut() {
+            db.sync_cursor = None;
+        }
+        self.version = CACHE_VERSION;
+    }
+
+    /// Make a queued change to the cached copy of its task, so the cache shows it before it's sent
+    /// The edit time is kept, so a pull still replaces the copy with Notion's once the change is sent
+    /// New tasks only get an ID from Notion and restored tasks aren't cached, so they show up after a sync
+    pub fn apply(&mut self, operation: &Operation) {
+        for db in self.databases.values_mut() {
+            match operation {
+                Operation::MarkAsDone { id } => {
+                    if let Some(task) = db.tasks.get_mut(id) {
+                        task.status = TaskStatus::Done;
+                    }
+                }
+                Operation::UpdateTask { id, to, name } => {
+                    if let Some(task) = db.tasks.get_mut(id) {
+                        if let Some(status) = to {
+                            task.status = *status;
+                        }
+                        if let Some(name) = name {
+                            task.title = name.clone();
+                        }
+                    }
+                }
+                Operation::ArchiveTask { id } => {
+                    db.tasks.remove(id);
+                }
+                Operation::AddTask { .. }
+                | Operation::RestoreTask { .. }
+                | Operation::AddComment { .. } => {}
+            }
+        }
+    }
+
+    /// Find a cached task in any database
+    pub fn find_task(&self, id: &str) -> Option<&Task> {
+        let id = id.replace('-', "");
+
+        return self
+            .databases
+            .values()
+            .flat_map(|db| db.tasks.values())
+            .find(|t| t.id.replace('-', "") == id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        models::task_status::TaskStatus,
+        services::fake::{task, time},
+    };
+
+    fn cached(tasks: Vec<Task>) -> DatabaseCache {
+        let mut db = DatabaseCache::default();
+        for task in tasks {
+            db.tasks.insert(task.id.clone(), task);
+        }
+
+        return db;
+    }
+
+    #[test]
+    fn upsert_keeps_a_newer_cached_copy() {
+        let mut db = cached(vec![task("1", "Newer", TaskStatus::Done, 10)]);
+
+        db.upsert(task("1", "Older", TaskStatus::Todo, 5));
+        assert_eq!(db.tasks["1"].title, "Newer");
+
+        db.upsert(task("1", "Same time", TaskStatus::Todo, 10));
+        assert_eq!(db.tasks["1"].title, "Same time");
+
+        db.upsert(task("2", "New", TaskStatus::Todo, 0));
+        assert_eq!(db.tasks.len(), 2);
+    }
+
+    #[test]
+    fn merge_drops_archived_tasks() {
+        let mut db = cached(vec![
+            task("1", "Keep", TaskStatus::Todo, 0),
+            task("2", "Archive", TaskStatus::Todo, 0),
+        ]);
+        let mut archived = task("2", "Archive", TaskStatus::Todo, 5);
+        archived.archived = true;
+        let mut never_cached = task("3", "Gone", TaskStatus::Todo, 5);
+        never_cached.archived = true;
+
+        let (updated, removed) = db.merge(vec![
+            task("1", "Kept", TaskStatus::Doing, 5),
+            archived,
+            never_cached,
+        ]);
+
+        assert_eq!((updated, removed), (1, 1));
+        assert_eq!(db.tasks.keys().collect::<Vec<_>>(), vec!["1"]);
+        assert_eq!(db.tasks["1"].title, "Kept");
+    }
+
+    #[test]
+    fn retain_listed_and_remove_count_dropped_tasks() {
+        let mut db = cached(vec![
+            task("1", "One", TaskStatus::Todo, 0),
+            task("2", "Two", TaskStatus::Todo, 0),
+            task("3", "Three", TaskStatus::Todo, 0),
+        ]);
+
+        let listed = HashSet::from(["1".to_string(), "2".to_string(), "9".to_string()]);
+        assert_eq!(db.retain_listed(&listed), 1);
+        assert_eq!(db.remove(&["2".to_string(), "9".to_string()]), 1);
+        assert_eq!(db.tasks.keys().collect::<Vec<_>>(), vec!["1"]);
+    }
+
+    #[test]
+    fn old_caches_pull_everything_again() {
+        let mut cache = Cache {
+            version: 0,
+            databases: BTreeMap::from([(
+                "db".to_string(),
+                DatabaseCache {
+                    sync_cursor: Some(time(0)),
+                    ..cached(vec![task("1", "One", TaskStatus::Todo, 0)])
+                },
+            )]),
+        };
+
+        cache.upgrade();
+
+        assert_eq!(cache.version, CACHE_VERSION);
+        assert!(cache.databases["db"].sync_cursor.is_none());
+        assert_eq!(cache.databases["db"].tasks.len(), 1);
+        assert!(cache.find_task("1").is_some());
+    }
+}
@@ -0,0 +1,41 @@
+use colour::{blue, green};
+
+/// A comment left on a Notion task
+#[derive(Debug, Clone)]
+pub struct Comment {
+    /// Name of the user who wrote the comment
+    pub author: String,
+    /// When the comment was created, as an ISO 8601 timestamp
+    pub created_time: String,
+    /// Plain text content of the comment
+    pub text: String,
+}
+
+impl Comment {
+    /// Construct a new Comment
+    pub fn new(author: String, created_time: String, text: String) -> Comment {
+        return Comment {
+            author,
+            created_time,
+            text,
+        };
+    }
+
+    /// Pretty print a comment in alternating colours
+    pub fn print(&self, i: usize) {
+        // 2022-07-14T18:43:00.000Z -> 2022-07-14 18:43
+        let timestamp = self
+            .created_time
+            .get(..16)
+            .unwrap_or(&self.created_time)
+            .replace('T', " ");
+
+        let out = format!("{} ({} UTC)\n{}\n", self.author, timestamp, self.text);
+
+        if i.is_multiple_of(2) {
+            green!("{}", out)
+        } else {
+            blue!("{}", out);
+        }
+    }
+}
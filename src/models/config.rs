@@ -1,7 +1,361 @@
+use anyhow::{bail, Result};
 use serde::{Deserialize, Serialize};
 
+use super::{database, property_mapping::PropertyMapping};
+
+/// The app config, saved in ~/.notion-cli/config.json
 #[derive(Deserialize, Serialize, Default)]
 pub struct AppConfig {
+    /// The ID of the database task commands use unless told otherwise
     pub database_id: String,
+    /// The Notion integration token
     pub token: String,
+    /// How tasks map onto the database's properties, configs written before it existed use the default
+    #[serde(default)]
+    pub mapping: PropertyMapping,
+    /// Every database added to the config, task commands pick one with --database
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub databases: Vec<DatabaseConfig>,
+}
+
+/// A task database added to the config
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct DatabaseConfig {
+    /// The ID of the database
+    pub id: String,
+    /// The title of the database, shown next to its tasks when several databases are listed
+    pub title: String,
+    /// How tasks map onto the database's properties
+    #[serde(default)]
+    pub mapping: PropertyMapping,
+    /// A short name to pick the database with, e.g. work
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+}
+
+impl DatabaseConfig {
+    /// The alias of the database, or its title if it has none
+    pub fn name(&self) -> &str {
+        return self.alias.as_deref().unwrap_or(&self.title);
+    }
+}
+
+impl AppConfig {
+    /// The database task commands use unless told otherwise
+    pub fn default_database(&self) -> Result<DatabaseConfig> {
+        if self.database_id.is_empty() {
+            bail!("No database set, run `notion init` or `notion config database set <ID>`");
+        }
+
+        // the mapping of the default database is kept at the top level so older configs keep working
+        let mut database = self
+            .databases
+            .iter()
+            .find(|d| d.id == self.database_id)
+            .cloned()
+            .unwrap_or_else(|| DatabaseConfig {
+                id: self.database_id.clone(),
+                title: "default".to_string(),
+                mapping: PropertyMapping::default(),
+                alias: None,
+            });
+        database.mapping = self.mapping.clone();
+
+        return Ok(database);
+    }
+
+    /// Every database added to the config, the default database first
+    pub fn all_databases(&self) -> Result<Vec<DatabaseConfig>> {
+        let mut databases = vec![self.default_database()?];
+        databases.extend(
+            self.databases
+                .iter()
+                .filter(|d| d.id != self.database_id)
+                .cloned(),
+        );
+
+        return Ok(databases);
+    }
+
+    /// Find databases by a comma separated list of aliases, titles, IDs or Notion URLs
+    /// Aliases are tried first, then titles, both case insensitively, then IDs, only databases in the config are found
+    /// Without names the default database is returned
+    pub fn find_databases(&self, names: Option<&str>) -> Result<Vec<DatabaseConfig>> {
+        let Some(names) = names else {
+            return Ok(vec![self.default_database()?]);
+        };
+
+        let all = self.all_databases()?;
+        let mut found: Vec<DatabaseConfig> = Vec::new();
+
+        for name in names.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+            let database = find_by_name(&all, name)?;
+            if !found.contains(&database) {
+                found.push(database);
+            }
+        }
+
+        if found.is_empty() {
+            bail!("--database needs at least one database alias, title, ID or URL");
+        }
+
+        return Ok(found);
+    }
+
+    /// Find a single database by alias, title, ID or URL, see find_databases
+    pub fn find_database(&self, name: Option<&str>) -> Result<DatabaseConfig> {
+        let mut databases = self.find_databases(name)?;
+        if databases.len() > 1 {
+            bail!("only `tasks list` can use several databases at once, pick one with --database");
+        }
+
+        return Ok(databases.remove(0));
+    }
+
+    /// Add a database to the config, replacing the one with the same ID
+    /// Its alias is taken away from any other database, a database added again without one keeps its alias
+    pub fn add_database(&mut self, mut database: DatabaseConfig) {
+        if let Some(alias) = &database.alias {
+            for other in self.databases.iter_mut().filter(|d| d.id != database.id) {
+                if other
+                    .alias
+                    .as_deref()
+                    .is_some_and(|a| a.eq_ignore_ascii_case(alias))
+                {
+                    other.alias = None;
+                }
+            }
+        } else if let Some(existing) = self.databases.iter().find(|d| d.id == database.id) {
+            database.alias = existing.alias.clone();
+        }
+
+        match self.databases.iter_mut().find(|d| d.id == database.id) {
+            Some(existing) => *existing = database,
+            None => self.databases.push(database),
+        }
+    }
+}
+
+/// Find a database by alias, then title, then ID or URL
+fn find_by_name(all: &[DatabaseConfig], name: &str) -> Result<DatabaseConfig> {
+    if let Some(database) = all.iter().find(|d| {
+        d.alias
+            .as_deref()
+            .is_some_and(|a| a.eq_ignore_ascii_case(name))
+    }) {
+        return Ok(database.clone());
+    }
+
+    let titled: Vec<&DatabaseConfig> = all
+        .iter()
+        .filter(|d| d.title.eq_ignore_ascii_case(name))
+        .collect();
+    match titled.as_slice() {
+        [database] => return Ok((*database).clone()),
+        [] => {}
+        _ => bail!(
+            "{} databases are called '{}', pick one by ID or give it an alias with `notion config database add <ID> --alias <NAME>`",
+            titled.len(),
+            name
+        ),
+    }
+
+    let id = database::parse_id(name).ok();
+    if let Some(database) = all
+        .iter()
+        .find(|d| d.id == name || Some(&d.id) == id.as_ref())
+    {
+        return Ok(database.clone());
+    }
+
+    if let Some(id) = id {
+        bail!(
+            "database {} isn't in the config, so its properties aren't known, add it with `notion config database add {}`",
+            id,
+            id
+        );
+    }
+
+    bail!(
+        "no database called '{}' in the config, add it with `notion config database add <ID> --alias <NAME>`, configured: {}",
+        name,
+        all.iter().map(DatabaseConfig::name).collect::<Vec<_>>().join(", ")
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WORK_ID: &str = "59ad73ec-efbd-4b37-ad53-02c8f3b17c56";
+
+    fn database(id: &str, title: &str, alias: Option<&str>) -> DatabaseConfig {
+        return DatabaseConfig {
+            id: id.to_string(),
+            title: title.to_string(),
+            mapping: PropertyMapping::default(),
+            alias: alias.map(str::to_string),
+        };
+    }
+
+    fn config() -> AppConfig {
+        return AppConfig {
+            database_id: "db1".to_string(),
+            token: "token".to_string(),
+            mapping: PropertyMapping {
+                title: "Task".to_string(),
+                ..PropertyMapping::default()
+            },
+            databases: vec![
+                database("db1", "Personal", Some("me")),
+                database(WORK_ID, "Work", Some("work")),
+                database("db3", "Side", Some("personal")),
+            ],
+        };
+    }
+
+    fn ids(databases: Vec<DatabaseConfig>) -> Vec<String> {
+        return databases.into_iter().map(|d| d.id).collect();
+    }
+
+    #[test]
+    fn default_database_uses_the_top_level_mapping() {
+        let config = config();
+        let database = config.default_database().unwrap();
+
+        assert_eq!(database.id, "db1");
+        assert_eq!(database.alias.as_deref(), Some("me"));
+        assert_eq!(database.mapping.title, "Task");
+    }
+
+    #[test]
+    fn default_database_outside_the_list() {
+        let mut config = config();
+        config.database_id = "db9".to_string();
+        let database = config.default_database().unwrap();
+
+        assert_eq!(database.id, "db9");
+        assert_eq!(database.title, "default");
+        assert_eq!(database.mapping.title, "Task");
+        assert_eq!(ids(config.all_databases().unwrap())[0], "db9");
+    }
+
+    #[test]
+    fn default_database_needs_an_id() {
+        let mut config = config();
+        config.database_id = String::new();
+
+        assert!(config.default_database().is_err());
+        assert!(config.find_databases(None).is_err());
+    }
+
+    #[test]
+    fn finds_the_default_database_without_names() {
+        assert_eq!(ids(config().find_databases(None).unwrap()), vec!["db1"]);
+    }
+
+    #[test]
+    fn finds_databases_by_alias_title_and_id() {
+        let config = config();
+
+        assert_eq!(
+            ids(config.find_databases(Some("WORK")).unwrap()),
+            vec![WORK_ID]
+        );
+        assert_eq!(
+            ids(config.find_databases(Some("side")).unwrap()),
+            vec!["db3"]
+        );
+        assert_eq!(
+            ids(config.find_databases(Some("db3")).unwrap()),
+            vec!["db3"]
+        );
+        assert_eq!(
+            ids(config
+                .find_databases(Some("59AD73ECEFBD4B37AD5302C8F3B17C56"))
+                .unwrap()),
+            vec![WORK_ID]
+        );
+        assert_eq!(
+            ids(config
+                .find_databases(Some(
+                    "https://www.notion.so/Work-59ad73ecefbd4b37ad5302c8f3b17c56?v=1"
+                ))
+                .unwrap()),
+            vec![WORK_ID]
+        );
+    }
+
+    #[test]
+    fn aliases_win_over_titles() {
+        // the default database is titled Personal, but another one has the alias personal
+        assert_eq!(
+            ids(config().find_databases(Some("personal")).unwrap()),
+            vec!["db3"]
+        );
+    }
+
+    #[test]
+    fn finds_several_databases_once_each() {
+        assert_eq!(
+            ids(config().find_databases(Some("work, me,,Work")).unwrap()),
+            vec![WORK_ID, "db1"]
+        );
+        assert!(config().find_databases(Some(" , ")).is_err());
+    }
+
+    #[test]
+    fn rejects_ambiguous_titles() {
+        let mut config = config();
+        config.databases.push(database("db4", "work", None));
+        config.databases.push(database("db5", "Side", None));
+
+        // the alias still picks one
+        assert_eq!(
+            ids(config.find_databases(Some("work")).unwrap()),
+            vec![WORK_ID]
+        );
+        assert!(config.find_databases(Some("side")).is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_databases() {
+        let config = config();
+
+        assert!(config.find_databases(Some("home")).is_err());
+        assert!(config
+            .find_databases(Some("0123456789abcdef0123456789abcdef"))
+            .is_err());
+    }
+
+    #[test]
+    fn find_database_wants_one() {
+        let config = config();
+
+        assert_eq!(config.find_database(Some("work")).unwrap().id, WORK_ID);
+        assert!(config.find_database(Some("work,me")).is_err());
+    }
+
+    #[test]
+    fn adding_a_database_takes_its_alias_from_others() {
+        let mut config = config();
+        config.add_database(database("db4", "Home", Some("Work")));
+
+        assert_eq!(config.databases[1].alias, None);
+        assert_eq!(config.databases[3].alias.as_deref(), Some("Work"));
+        assert_eq!(config.find_database(Some("work")).unwrap().id, "db4");
+    }
+
+    #[test]
+    fn adding_a_database_again_replaces_it() {
+        let mut config = config();
+        config.add_database(database("db3", "Side projects", None));
+
+        assert_eq!(config.databases.len(), 3);
+        assert_eq!(config.databases[2].title, "Side projects");
+        assert_eq!(config.databases[2].alias.as_deref(), Some("personal"));
+
+        config.add_database(database("db3", "Side projects", Some("side")));
+        assert_eq!(config.databases[2].alias.as_deref(), Some("side"));
+    }
 }
@@ -1,25 +1,114 @@
+use anyhow::{bail, Result};
 use colour::{blue_ln, green_ln};
 
+use super::property_mapping::PropertyMapping;
+
 /// Represents a notion database object
 pub struct Database {
     /// The title of the database
-    title: String,
+    pub title: String,
     /// The ID of the database
-    id: String,
+    pub id: String,
+    /// The properties tasks in the database are read from
+    pub mapping: PropertyMapping,
 }
 
 impl Database {
     /// Return a new Database
-    pub fn new(id: String, title: String) -> Database {
-        return Database { title, id };
+    pub fn new(id: String, title: String, mapping: PropertyMapping) -> Database {
+        return Database { title, id, mapping };
     }
 
     /// Print a database in alternating colours
     pub fn print(&self, i: usize) {
-        if i % 2 == 0 {
+        if i.is_multiple_of(2) {
             green_ln!("Database: {} | ID: {}", self.title, self.id)
         } else {
             blue_ln!("Database: {} | ID: {}", self.title, self.id);
         }
     }
 }
+
+/// Turn a database or page ID, with or without dashes, or a Notion URL into a dashed lowercase UUID
+/// URLs end with the page title followed by the ID, e.g. `https://www.notion.so/Tasks-59ad73ecefbd4b37ad5302c8f3b17c56?v=...`
+/// Anything that isn't a URL has to be exactly an ID, so a mistyped extra character isn't read as a different ID
+pub fn parse_id(input: &str) -> Result<String> {
+    let path = input.trim().split(['?', '#']).next().unwrap_or_default();
+    let is_url = path.contains('/');
+    let last = path
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .unwrap_or_default();
+    let hex: Vec<char> = last.chars().filter(|c| *c != '-').collect();
+
+    // the title in front of the ID in a URL can end in hex characters, so only the last 32 are the ID
+    if hex.len() < 32
+        || (!is_url && hex.len() > 32)
+        || !hex[hex.len() - 32..].iter().all(|c| c.is_ascii_hexdigit())
+    {
+        bail!(
+            "'{}' isn't a Notion ID or URL, copy the ID from `notion config database list` or the link from Notion",
+            input
+        );
+    }
+
+    let id: String = hex[hex.len() - 32..]
+        .iter()
+        .collect::<String>()
+        .to_lowercase();
+
+    return Ok(format!(
+        "{}-{}-{}-{}-{}",
+        &id[..8],
+        &id[8..12],
+        &id[12..16],
+        &id[16..20],
+        &id[20..]
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ID: &str = "59ad73ec-efbd-4b37-ad53-02c8f3b17c56";
+
+    #[test]
+    fn parses_ids() {
+        assert_eq!(parse_id(ID).unwrap(), ID);
+        assert_eq!(parse_id(" 59AD73ECEFBD4B37AD5302C8F3B17C56 ").unwrap(), ID);
+    }
+
+    #[test]
+    fn parses_urls() {
+        assert_eq!(
+            parse_id("https://www.notion.so/Tasks-59ad73ecefbd4b37ad5302c8f3b17c56?v=8c1f")
+                .unwrap(),
+            ID
+        );
+        assert_eq!(
+            parse_id("https://www.notion.so/workspace/My-Weekly-Tasks-59ad73ecefbd4b37ad5302c8f3b17c56#abc").unwrap(),
+            ID
+        );
+        assert_eq!(
+            parse_id("notion.so/Cafe-Bed-59ad73ecefbd4b37ad5302c8f3b17c56/").unwrap(),
+            ID
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_input() {
+        for input in [
+            "",
+            "work",
+            "59ad73ecefbd4b37ad5302c8f3b17c5",
+            "59ad73ecefbd4b37ad5302c8f3b17c56a",
+            "a59ad73ec-efbd-4b37-ad53-02c8f3b17c56",
+            "59ad73ecefbd4b37ad5302c8f3b17cxy",
+            "https://www.notion.so/Tasks",
+        ] {
+            assert!(parse_id(input).is_err(), "'{}' should be rejected", input);
+        }
+    }
+}
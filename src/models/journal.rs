@@ -0,0 +1,104 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::task_status::TaskStatus;
+
+/// Changes that couldn't be sent to Notion, waiting for `notion sync`
+#[derive(Deserialize, Serialize, Default)]
+pub struct Journal {
+    /// Queued changes, oldest first
+    pub entries: Vec<JournalEntry>,
+}
+
+/// A single queued change
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct JournalEntry {
+    /// When the change was queued
+    pub queued_at: DateTime<Utc>,
+    /// When the page was last edited as far as we knew when queueing, used to detect conflicts
+    pub base_edited_time: Option<DateTime<Utc>>,
+    /// The database the change was made in, its property mapping is used to replay it
+    /// Entries queued before databases were recorded are replayed in the default database
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub database_id: Option<String>,
+    /// The change to make
+    pub operation: Operation,
+}
+
+/// A change to a Notion database
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Operation {
+    /// Add a task
+    AddTask {
+        /// The database to add the task to
+        database_id: String,
+        /// The task title
+        title: String,
+        /// The task status
+        status: TaskStatus,
+        /// Page content blocks
+        body: Vec<Value>,
+        /// Values for the task's other properties
+        #[serde(default)]
+        properties: Map<String, Value>,
+    },
+    /// Mark a task as done
+    MarkAsDone {
+        /// The task ID
+        id: String,
+    },
+    /// Change a task's status and/or title
+    UpdateTask {
+        /// The task ID
+        id: String,
+        /// The new status
+        to: Option<TaskStatus>,
+        /// The new title
+        name: Option<String>,
+    },
+    /// Archive a task
+    ArchiveTask {
+        /// The task ID
+        id: String,
+    },
+    /// Restore an archived task
+    RestoreTask {
+        /// The task ID
+        id: String,
+    },
+    /// Comment on a task
+    AddComment {
+        /// The task ID
+        id: String,
+        /// The comment text
+        text: String,
+    },
+}
+
+impl Operation {
+    /// The ID of the task the change applies to, None for new tasks
+    pub fn task_id(&self) -> Option<&str> {
+        return match self {
+            Operation::AddTask { .. } => None,
+            Operation::MarkAsDone { id }
+            | Operation::UpdateTask { id, .. }
+            | Operation::ArchiveTask { id }
+            | Operation::RestoreTask { id }
+            | Operation::AddComment { id, .. } => Some(id),
+        };
+    }
+
+    /// A short human readable description of the change
+    pub fn describe(&self) -> String {
+        return match self {
+            Operation::AddTask { title, .. } => format!("add task '{}'", title),
+            Operation::MarkAsDone { id } => format!("mark {} as done", id),
+            Operation::UpdateTask { id, .. } => format!("update {}", id),
+            Operation::ArchiveTask { id } => format!("archive {}", id),
+            Operation::RestoreTask { id } => format!("restore {}", id),
+            Operation::AddComment { id, .. } => format!("comment on {}", id),
+        };
+    }
+}
@@ -0,0 +1,9 @@
+/// How `tasks list` prints the tasks
+#[derive(clap::ValueEnum, Clone, Debug, Copy, PartialEq, Eq, Default)]
+pub enum ListOutput {
+    /// Coloured task details with handles
+    #[default]
+    Pretty,
+    /// One task ID per line, for piping into other commands
+    Ids,
+}
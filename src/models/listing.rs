@@ -0,0 +1,31 @@
+use serde::{Deserialize, Serialize};
+
+/// The tasks shown by the last `tasks list`, in the order they were printed
+/// Lets tasks be referred to by their position in that listing
+#[derive(Deserialize, Serialize, Default)]
+pub struct Listing {
+    /// The database the tasks were listed from, the first one when several were listed
+    pub database_id: String,
+    /// IDs of the listed tasks, index 0 was printed as 1
+    pub task_ids: Vec<String>,
+    /// The database of each listed task, empty in listings written before several databases could be listed
+    #[serde(default)]
+    pub database_ids: Vec<String>,
+}
+
+impl Listing {
+    /// Whether any listed task came from the database
+    pub fn includes(&self, database_id: &str) -> bool {
+        return self.database_id == database_id
+            || self.database_ids.iter().any(|d| d == database_id);
+    }
+
+    /// The database the task at index came from
+    pub fn database_of(&self, index: usize) -> &str {
+        return self
+            .database_ids
+            .get(index)
+            .unwrap_or(&self.database_id)
+            .as_str();
+    }
+}
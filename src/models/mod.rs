@@ -1,4 +1,30 @@
+/// Typed Notion API objects, as Notion sends and receives them
+pub mod api;
+/// The local copy of databases used offline
+pub mod cache;
+/// Module for task comments
+pub mod comment;
+/// Module for the app config
 pub mod config;
+/// Module for task databases and parsing their IDs
 pub mod database;
+/// Changes queued while Notion couldn't be reached
+pub mod journal;
+/// Module for the output formats of `tasks list`
+pub mod list_output;
+/// The last task listing, so tasks can be referred to by index
+pub mod listing;
+/// Module for matching tasks by title
+pub mod name_match;
+/// Which database properties hold the task title and status
+pub mod property_mapping;
+/// Module for tasks
 pub mod task;
+/// Module for filtering and sorting tasks
+pub mod task_filter;
+/// Module for the import and export file formats
+pub mod task_format;
+/// Module for picking tasks by ID, index or name
+pub mod task_selector;
+/// Module for task statuses
 pub mod task_status;
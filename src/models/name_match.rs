@@ -0,0 +1,216 @@
+use std::cmp::Reverse;
+
+use anyhow::{bail, Result};
+
+use super::task::Task;
+
+/// How closely a task title matches a search pattern, best first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MatchKind {
+    /// The title is the pattern
+    Exact,
+    /// The title starts with the pattern
+    Prefix,
+    /// The title contains the pattern
+    Substring,
+    /// The pattern's characters appear in the title in order
+    Fuzzy,
+}
+
+/// Return every task matching the pattern and how closely it matches, best match first
+/// Matching is case insensitive. Ties are broken by fuzzy score, then by shorter title
+pub fn rank_by_name(tasks: Vec<Task>, pattern: &str) -> Result<Vec<(MatchKind, Task)>> {
+    let pattern = pattern.trim().to_lowercase();
+    if pattern.is_empty() {
+        bail!("the name to match can't be empty");
+    }
+
+    let mut ranked: Vec<(MatchKind, i64, Task)> = tasks
+        .into_iter()
+        .filter_map(|task| {
+            let title = task.title.trim().to_lowercase();
+
+            let (kind, score) = if title == pattern {
+                (MatchKind::Exact, 0)
+            } else if title.starts_with(&pattern) {
+                (MatchKind::Prefix, 0)
+            } else if title.contains(&pattern) {
+                (MatchKind::Substring, 0)
+            } else {
+                (MatchKind::Fuzzy, fuzzy_score(&title, &pattern)?)
+            };
+
+            return Some((kind, score, task));
+        })
+        .collect();
+
+    ranked.sort_by_key(|(kind, score, task)| (*kind, Reverse(*score), task.title.len()));
+
+    return Ok(ranked
+        .into_iter()
+        .map(|(kind, _, task)| (kind, task))
+        .collect());
+}
+
+/// The tasks in the best tier of ranked matches, to pick one from
+/// Only exact matches are kept if there are any, else only prefix matches, and so on down to fuzzy ones,
+/// so a loose match never rides along with a close one
+pub fn best_matches(ranked: Vec<(MatchKind, Task)>) -> Vec<Task> {
+    let Some(best) = ranked.first().map(|(kind, _)| *kind) else {
+        return Vec::new();
+    };
+
+    return ranked
+        .into_iter()
+        .take_while(|(kind, _)| *kind == best)
+        .map(|(_, task)| task)
+        .collect();
+}
+
+/// The tasks to act on when every match is wanted: every title that is, starts with or contains the pattern
+/// Fuzzy matches are only taken when nothing matches more closely, they are too loose to act on alongside others
+pub fn all_matches(ranked: Vec<(MatchKind, Task)>) -> Vec<Task> {
+    let closest = ranked.first().map(|(kind, _)| *kind);
+
+    return ranked
+        .into_iter()
+        .filter(|(kind, _)| *kind != MatchKind::Fuzzy || closest == Some(MatchKind::Fuzzy))
+        .map(|(_, task)| task)
+        .collect();
+}
+
+/// Score a subsequence match of pattern in title, higher is better
+/// Consecutive characters and characters at the start of a word earn a bonus, gaps cost a point per skipped character
+fn fuzzy_score(title: &str, pattern: &str) -> Option<i64> {
+    let title: Vec<char> = title.chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous: Option<usize> = None;
+
+    for p in pattern.chars().filter(|c| !c.is_whitespace()) {
+        let found = position + title[position..].iter().position(|c| *c == p)?;
+
+        score += 1;
+        if found == 0 || !title[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        match previous {
+            Some(prev) if prev + 1 == found => score += 5,
+            Some(prev) => score -= (found - prev - 1) as i64,
+            None => {}
+        }
+
+        previous = Some(found);
+        position = found + 1;
+    }
+
+    return Some(score);
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+    use crate::models::task_status::TaskStatus;
+
+    /// Tasks with the given titles, their IDs are their titles
+    fn tasks(titles: &[&str]) -> Vec<Task> {
+        return titles
+            .iter()
+            .map(|title| {
+                Task::new(
+                    title.to_string(),
+                    TaskStatus::Todo,
+                    title.to_string(),
+                    Utc::now(),
+                    false,
+                    Default::default(),
+                )
+            })
+            .collect();
+    }
+
+    /// The titles of the best matches for pattern, in order
+    fn titles(candidates: &[&str], pattern: &str) -> Vec<String> {
+        let ranked = rank_by_name(tasks(candidates), pattern).unwrap();
+
+        return best_matches(ranked).into_iter().map(|t| t.title).collect();
+    }
+
+    /// The titles of every match for pattern, in order
+    fn all_titles(candidates: &[&str], pattern: &str) -> Vec<String> {
+        let ranked = rank_by_name(tasks(candidates), pattern).unwrap();
+
+        return all_matches(ranked).into_iter().map(|t| t.title).collect();
+    }
+
+    #[test]
+    fn exact_match_hides_looser_ones() {
+        assert_eq!(
+            titles(
+                &["Feed the cat", "Cat", "Clean attic", "Catalogue books"],
+                "cat"
+            ),
+            vec!["Cat"]
+        );
+    }
+
+    #[test]
+    fn prefix_matches_shortest_first() {
+        assert_eq!(
+            titles(&["Catalogue books", "Cats", "Feed the cat"], "CAT"),
+            vec!["Cats", "Catalogue books"]
+        );
+    }
+
+    #[test]
+    fn substring_matches_hide_fuzzy_ones() {
+        assert_eq!(
+            titles(&["Feed the cat", "Clean attic"], "cat"),
+            vec!["Feed the cat"]
+        );
+    }
+
+    #[test]
+    fn fuzzy_matches_prefer_word_starts() {
+        assert_eq!(
+            titles(&["Clean attic", "Write quarterly report"], "wqr"),
+            vec!["Write quarterly report"]
+        );
+        assert_eq!(
+            titles(&["Send the contract", "Send contract"], "sct"),
+            vec!["Send contract", "Send the contract"]
+        );
+    }
+
+    #[test]
+    fn all_matches_include_every_close_tier() {
+        assert_eq!(
+            all_titles(
+                &["Feed the cat", "Cat", "Clean attic", "Catalogue books"],
+                "cat"
+            ),
+            vec!["Cat", "Catalogue books", "Feed the cat"]
+        );
+    }
+
+    #[test]
+    fn all_matches_are_fuzzy_only_without_closer_ones() {
+        assert_eq!(
+            all_titles(&["Send the contract", "Send contract"], "sct"),
+            vec!["Send contract", "Send the contract"]
+        );
+    }
+
+    #[test]
+    fn no_match_is_empty() {
+        assert!(titles(&["Feed the cat"], "dog").is_empty());
+        assert!(all_titles(&["Feed the cat"], "dog").is_empty());
+    }
+
+    #[test]
+    fn empty_pattern_is_rejected() {
+        assert!(rank_by_name(tasks(&["Feed the cat"]), "  ").is_err());
+    }
+}
@@ -0,0 +1,302 @@
+use std::fmt::Display;
+
+use super::{
+    api::{
+        filter::{Filter, PropertyCondition, SelectCondition},
+        schema::{PropertySchema, Schema},
+    },
+    task_status::TaskStatus,
+};
+use serde::{Deserialize, Serialize};
+
+/// Which database properties hold a task's title and status, and which status options mean todo, doing and done
+/// Defaults to the Task List template: Name, and a Status select with To Do, Doing and Done🙌
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct PropertyMapping {
+    /// Name of the title property
+    pub title: String,
+    /// Name of the status property
+    pub status: String,
+    /// Type of the status property, select or status
+    pub status_type: String,
+    /// Status option for tasks that haven't been started
+    pub todo: String,
+    /// Status option for tasks in progress
+    pub doing: String,
+    /// Status option for finished tasks
+    pub done: String,
+}
+
+impl Default for PropertyMapping {
+    fn default() -> Self {
+        return PropertyMapping {
+            title: "Name".to_string(),
+            status: "Status".to_string(),
+            status_type: "select".to_string(),
+            todo: "To Do".to_string(),
+            doing: "Doing".to_string(),
+            done: "Done🙌".to_string(),
+        };
+    }
+}
+
+/// Normalized option names recognised as each status when detecting a mapping
+const TODO_OPTIONS: [&str; 3] = ["todo", "notstarted", "backlog"];
+const DOING_OPTIONS: [&str; 3] = ["doing", "inprogress", "started"];
+const DONE_OPTIONS: [&str; 4] = ["done", "complete", "completed", "finished"];
+
+impl PropertyMapping {
+    /// The status option a task status is stored as
+    pub fn option_name(&self, status: TaskStatus) -> &str {
+        return match status {
+            TaskStatus::Todo => &self.todo,
+            TaskStatus::Doing => &self.doing,
+            TaskStatus::Done => &self.done,
+        };
+    }
+
+    /// The task status a status option stands for
+    pub fn status_of(&self, option: &str) -> Option<TaskStatus> {
+        return [TaskStatus::Todo, TaskStatus::Doing, TaskStatus::Done]
+            .into_iter()
+            .find(|s| self.option_name(*s) == option);
+    }
+
+    /// Work out the mapping from a database property schema
+    /// The title property is the one of type title, the status property is the first select or status
+    /// property, preferring one named Status, with options that look like todo, doing and done
+    pub fn detect(schema: &Schema) -> Option<PropertyMapping> {
+        let title = schema
+            .iter()
+            .find(|(_, p)| matches!(p, PropertySchema::Title { .. }))
+            .map(|(name, _)| name.clone())?;
+
+        let mut candidates: Vec<(&String, &PropertySchema)> = schema
+            .iter()
+            .filter(|(_, p)| {
+                matches!(
+                    p,
+                    PropertySchema::Select { .. } | PropertySchema::Status { .. }
+                )
+            })
+            .collect();
+        candidates.sort_by_key(|(name, _)| normalize(name) != "status");
+
+        for (name, property) in candidates {
+            let options = property.option_names();
+
+            let find = |names: &[&str]| {
+                options
+                    .iter()
+                    .find(|o| names.contains(&normalize(o).as_str()))
+                    .map(|o| o.to_string())
+            };
+
+            if let (Some(todo), Some(doing), Some(done)) = (
+                find(&TODO_OPTIONS),
+                find(&DOING_OPTIONS),
+                find(&DONE_OPTIONS),
+            ) {
+                return Some(PropertyMapping {
+                    title,
+                    status: name.clone(),
+                    status_type: property.kind().to_string(),
+                    todo,
+                    doing,
+                    done,
+                });
+            }
+        }
+
+        return None;
+    }
+
+    /// What a database property schema is missing for this mapping to work, empty if nothing is
+    pub fn problems(&self, schema: &Schema) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        match schema.get(&self.title) {
+            Some(PropertySchema::Title { .. }) => {}
+            Some(property) => problems.push(format!(
+                "the {} property is a {} property, not the title",
+                self.title,
+                property.kind()
+            )),
+            None => problems.push(format!("there is no {} title property", self.title)),
+        }
+
+        match schema.get(&self.status) {
+            Some(property) if property.kind() == self.status_type => {
+                let options = property.option_names();
+
+                for option in [&self.todo, &self.doing, &self.done] {
+                    if !options.contains(&option.as_str()) {
+                        problems.push(format!(
+                            "the {} property has no {} option",
+                            self.status, option
+                        ));
+                    }
+                }
+            }
+            Some(property) => problems.push(format!(
+                "the {} property is a {} property, not a {}",
+                self.status,
+                property.kind(),
+                self.status_type
+            )),
+            None => problems.push(format!(
+                "there is no {} {} property",
+                self.status, self.status_type
+            )),
+        }
+
+        return problems;
+    }
+
+    /// A query filter matching the tasks with a status
+    pub fn status_filter(&self, status: TaskStatus) -> Filter {
+        let condition = SelectCondition::Equals(self.option_name(status).to_string());
+
+        return Filter::Property {
+            property: self.status.clone(),
+            condition: match self.status_type.as_str() {
+                "status" => PropertyCondition::Status(condition),
+                _ => PropertyCondition::Select(condition),
+            },
+        };
+    }
+}
+
+/// Lowercase letters and digits only, so "To Do" and "Done🙌" compare as todo and done
+fn normalize(name: &str) -> String {
+    return name
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect();
+}
+
+impl Display for PropertyMapping {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "title: {}, status: {} ({} / {} / {})",
+            self.title, self.status, self.todo, self.doing, self.done
+        )?;
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    /// A select or status property schema with the given options
+    fn options(kind: &str, names: &[&str]) -> Value {
+        let options: Vec<Value> = names.iter().map(|n| json!({ "name": n })).collect();
+
+        return json!({ "type": kind, kind: { "options": options } });
+    }
+
+    /// A schema with a Name title property and the given other properties
+    fn schema(properties: Value) -> Schema {
+        let mut properties = properties;
+        properties["Name"] = json!({ "type": "title", "title": {} });
+
+        return serde_json::from_value(properties).expect("invalid schema");
+    }
+
+    #[test]
+    fn detects_the_default_template() {
+        let schema = schema(json!({
+            "Status": options("select", &["To Do", "Doing", "Done🙌"]),
+        }));
+
+        assert_eq!(
+            PropertyMapping::detect(&schema),
+            Some(PropertyMapping::default())
+        );
+        assert!(PropertyMapping::default().problems(&schema).is_empty());
+    }
+
+    #[test]
+    fn detects_a_status_property() {
+        let schema = schema(json!({
+            "Stage": options("status", &["Not started", "In progress", "Done"]),
+        }));
+        let mapping = PropertyMapping::detect(&schema).unwrap();
+
+        assert_eq!(mapping.status, "Stage");
+        assert_eq!(mapping.status_type, "status");
+        assert_eq!(mapping.todo, "Not started");
+        assert_eq!(mapping.doing, "In progress");
+        assert_eq!(mapping.done, "Done");
+    }
+
+    #[test]
+    fn detects_renamed_options() {
+        let schema = schema(json!({
+            "State": options("select", &["Backlog", "Won't do", "IN-PROGRESS", "Finished ✅"]),
+        }));
+        let mapping = PropertyMapping::detect(&schema).unwrap();
+
+        assert_eq!(mapping.todo, "Backlog");
+        assert_eq!(mapping.doing, "IN-PROGRESS");
+        assert_eq!(mapping.done, "Finished ✅");
+        assert_eq!(mapping.status_of("IN-PROGRESS"), Some(TaskStatus::Doing));
+        assert_eq!(mapping.status_of("Won't do"), None);
+        assert_eq!(mapping.option_name(TaskStatus::Done), "Finished ✅");
+    }
+
+    #[test]
+    fn prefers_the_select_named_status() {
+        let schema = schema(json!({
+            "Phase": options("select", &["Todo", "Doing", "Done"]),
+            "Priority": options("select", &["Low", "High"]),
+            "status": options("select", &["Not started", "Started", "Complete"]),
+        }));
+        let mapping = PropertyMapping::detect(&schema).unwrap();
+
+        assert_eq!(mapping.status, "status");
+        assert_eq!(mapping.todo, "Not started");
+    }
+
+    #[test]
+    fn skips_selects_without_task_options() {
+        let schema = schema(json!({
+            "Area": options("select", &["Home", "Work"]),
+            "Progress": options("select", &["Todo", "Doing", "Done"]),
+        }));
+
+        assert_eq!(PropertyMapping::detect(&schema).unwrap().status, "Progress");
+    }
+
+    #[test]
+    fn nothing_detected_without_title_or_statuses() {
+        let without_statuses = schema(json!({
+            "Status": options("select", &["Todo", "Done"]),
+        }));
+        let without_title: Schema = serde_json::from_value(json!({
+            "Status": options("select", &["Todo", "Doing", "Done"]),
+        }))
+        .unwrap();
+
+        assert_eq!(PropertyMapping::detect(&without_statuses), None);
+        assert_eq!(PropertyMapping::detect(&without_title), None);
+    }
+
+    #[test]
+    fn reports_problems_with_the_schema() {
+        let schema = schema(json!({
+            "Status": options("status", &["To Do", "Doing"]),
+        }));
+        let problems = PropertyMapping::default().problems(&schema);
+
+        assert_eq!(
+            problems,
+            vec!["the Status property is a status property, not a select"]
+        );
+    }
+}
@@ -1,9 +1,14 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
 use colour::{blue, green};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 
 use super::task_status::TaskStatus;
 
 /// A Notion task
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Task {
     /// The task's ID
     pub id: String,
@@ -11,19 +16,79 @@ pub struct Task {
     pub status: TaskStatus,
     /// Title of the task
     pub title: String,
+    /// When the task was last edited
+    pub last_edited_time: DateTime<Utc>,
+    /// Whether the task has been archived
+    #[serde(default)]
+    pub archived: bool,
+    /// The page's other properties as plain text, keyed by property name
+    #[serde(default)]
+    pub properties: BTreeMap<String, String>,
+}
+
+/// A task to add to a database, it gets its ID from Notion
+#[derive(Debug, Clone)]
+pub struct NewTask {
+    /// Title of the task
+    pub title: String,
+    /// The task's status
+    pub status: TaskStatus,
+    /// The blocks that make up the page content
+    pub body: Vec<Value>,
+    /// Values for properties other than the title and status
+    pub properties: Map<String, Value>,
+}
+
+/// The pages of a database edited since some time
+#[derive(Debug, Default)]
+pub struct EditedTasks {
+    /// The edited pages that are tasks
+    pub tasks: Vec<Task>,
+    /// IDs of the edited pages that are no longer tasks, e.g. because their status was cleared
+    pub not_tasks: Vec<String>,
 }
 
 impl Task {
     /// Construct a new Task instance with an ID and status
-    pub fn new(id: String, status: TaskStatus, title: String) -> Task {
-        return Task { id, status, title };
+    pub fn new(
+        id: String,
+        status: TaskStatus,
+        title: String,
+        last_edited_time: DateTime<Utc>,
+        archived: bool,
+        properties: BTreeMap<String, String>,
+    ) -> Task {
+        return Task {
+            id,
+            status,
+            title,
+            last_edited_time,
+            archived,
+            properties,
+        };
     }
 
-    /// Pretty print a task
-    pub fn print(&self, i: usize, with_status: bool, with_id: bool) {
+    /// Pretty print a task, handle is the short way of referring to it shown by listings
+    /// and source the database it came from when tasks of several databases are shown together
+    pub fn print(
+        &self,
+        i: usize,
+        handle: Option<&str>,
+        source: Option<&str>,
+        with_status: bool,
+        with_id: bool,
+    ) {
         let mut out = String::new();
         out.push_str(format!("Task: {}\n", self.title).as_str());
 
+        if let Some(s) = source {
+            out.push_str(format!("Database: {}\n", s).as_str());
+        }
+
+        if let Some(h) = handle {
+            out.push_str(format!("Handle: {}\n", h).as_str());
+        }
+
         if with_status {
             out.push_str(format!("Status: {}\n", self.status).as_str());
         }
@@ -32,7 +97,7 @@ impl Task {
             out.push_str(format!("ID: {}\n", self.id).as_str());
         }
 
-        if i % 2 == 0 {
+        if i.is_multiple_of(2) {
             green!("{}", out)
         } else {
             blue!("{}", out);
@@ -0,0 +1,300 @@
+use std::str::FromStr;
+
+use anyhow::{bail, Error, Result};
+use chrono::{Duration, Utc};
+use clap::ValueEnum;
+
+use super::{task::Task, task_status::TaskStatus};
+
+/// A filter expression such as `status!=done and title~cat and age>30d`
+/// Clauses are joined with `and`, every clause has to match. Values containing spaces are quoted, e.g. `title~"salt and pepper"`
+#[derive(Debug, Clone)]
+pub struct TaskFilter {
+    /// The clauses of the expression
+    clauses: Vec<Clause>,
+}
+
+/// A single comparison in a filter expression
+#[derive(Debug, Clone)]
+enum Clause {
+    /// Compare the title, case insensitive
+    Title(TextOp, String),
+    /// Compare the ID, dashes are ignored
+    Id(TextOp, String),
+    /// Compare the status
+    Status(bool, TaskStatus),
+    /// Compare the time since the task was last edited, true means older than
+    Age(bool, Duration),
+}
+
+/// Comparisons that can be made on text fields
+#[derive(Debug, Clone, Copy)]
+enum TextOp {
+    /// =
+    Equals,
+    /// !=
+    NotEquals,
+    /// ~
+    Contains,
+    /// !~
+    NotContains,
+}
+
+impl TaskFilter {
+    /// Whether the task matches every clause
+    pub fn matches(&self, task: &Task) -> bool {
+        return self.clauses.iter().all(|clause| match clause {
+            Clause::Title(op, value) => op.apply(&task.title.to_lowercase(), value),
+            Clause::Id(op, value) => op.apply(&task.id.replace('-', ""), value),
+            Clause::Status(equals, status) => (task.status == *status) == *equals,
+            Clause::Age(older, age) => {
+                let cutoff = Utc::now().checked_sub_signed(*age);
+                cutoff.is_some_and(|c| task.last_edited_time < c) == *older
+            }
+        });
+    }
+}
+
+impl TextOp {
+    /// Compare a field against a value
+    fn apply(&self, field: &str, value: &str) -> bool {
+        return match self {
+            TextOp::Equals => field == value,
+            TextOp::NotEquals => field != value,
+            TextOp::Contains => field.contains(value),
+            TextOp::NotContains => !field.contains(value),
+        };
+    }
+}
+
+impl FromStr for TaskFilter {
+    type Err = Error;
+
+    fn from_str(expression: &str) -> Result<Self> {
+        let mut clauses = Vec::new();
+
+        for clause in split_clauses(expression)? {
+            clauses.push(parse_clause(&clause)?);
+        }
+
+        return Ok(TaskFilter { clauses });
+    }
+}
+
+/// Split an expression into clauses on the word `and`, except inside single or double quotes
+fn split_clauses(expression: &str) -> Result<Vec<String>> {
+    let mut clauses = Vec::new();
+    let mut words: Vec<String> = Vec::new();
+    let mut word = String::new();
+    let mut quote: Option<char> = None;
+
+    // a space outside quotes ends a word, a word that is just `and` ends a clause
+    for c in expression.chars().chain([' ']) {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c.is_whitespace() => {
+                if word.eq_ignore_ascii_case("and") {
+                    clauses.push(words.join(" "));
+                    words.clear();
+                } else if !word.is_empty() {
+                    words.push(word.clone());
+                }
+                word.clear();
+                continue;
+            }
+            None => {}
+        }
+        word.push(c);
+    }
+
+    if let Some(q) = quote {
+        bail!("unterminated {} quote in '{}'", q, expression);
+    }
+    clauses.push(words.join(" "));
+
+    if let Some(empty) = clauses.iter().position(|c| c.is_empty()) {
+        bail!(
+            "clause {} of '{}' is empty, join clauses with a single `and`",
+            empty + 1,
+            expression
+        );
+    }
+
+    return Ok(clauses);
+}
+
+/// Parse a clause like `title~cat` into a field, operator and value
+fn parse_clause(clause: &str) -> Result<Clause> {
+    let Some(start) = clause.find(['=', '!', '~', '<', '>']) else {
+        bail!(
+            "'{}' is missing an operator, use one of =, !=, ~, !~, < or >",
+            clause
+        );
+    };
+    let field = clause[..start].trim().to_lowercase();
+    let rest = &clause[start..];
+    let op_len = if rest.starts_with("!=") || rest.starts_with("!~") {
+        2
+    } else {
+        1
+    };
+    let (op, value) = rest.split_at(op_len);
+    let value = unquote(value.trim());
+
+    let text_op = match op {
+        "=" => Some(TextOp::Equals),
+        "!=" => Some(TextOp::NotEquals),
+        "~" => Some(TextOp::Contains),
+        "!~" => Some(TextOp::NotContains),
+        _ => None,
+    };
+
+    return match (field.as_str(), op, text_op) {
+        ("title" | "name", _, Some(op)) => Ok(Clause::Title(op, value.to_lowercase())),
+        ("id", _, Some(op)) => Ok(Clause::Id(op, value.replace('-', "").to_lowercase())),
+        ("status", "=" | "!=", _) => match TaskStatus::from_str(value, true) {
+            Ok(status) => Ok(Clause::Status(op == "=", status)),
+            Err(_) => bail!("unknown status '{}', use one of todo, doing or done", value),
+        },
+        ("age", "<" | ">", _) => Ok(Clause::Age(op == ">", parse_age(value)?)),
+        ("title" | "name" | "id" | "status" | "age", _, _) => {
+            bail!("operator '{}' can't be used with {}", op, field)
+        }
+        _ => bail!(
+            "unknown field '{}', use one of title, id, status or age",
+            field
+        ),
+    };
+}
+
+/// Remove a pair of single or double quotes around a value
+fn unquote(value: &str) -> &str {
+    for q in ['"', '\''] {
+        if let Some(inner) = value.strip_prefix(q).and_then(|v| v.strip_suffix(q)) {
+            return inner;
+        }
+    }
+
+    return value;
+}
+
+/// Parse an age like 30d, 2w, 12h or 45m
+pub fn parse_age(value: &str) -> Result<Duration> {
+    let value = value.trim();
+    let Some(split) = value.find(|c: char| !c.is_ascii_digit()) else {
+        bail!("missing unit in '{}', use one of m, h, d or w", value);
+    };
+    let (amount, unit) = value.split_at(split);
+
+    let Ok(amount) = amount.parse::<i64>() else {
+        bail!("invalid amount in '{}'", value);
+    };
+
+    let age = match unit {
+        "m" => Duration::try_minutes(amount),
+        "h" => Duration::try_hours(amount),
+        "d" => Duration::try_days(amount),
+        "w" => Duration::try_weeks(amount),
+        _ => bail!("unknown unit '{}', use one of m, h, d or w", unit),
+    };
+
+    // ages reaching back before the earliest date that can be represented are rejected as well
+    match age {
+        Some(age) if Utc::now().checked_sub_signed(age).is_some() => return Ok(age),
+        _ => bail!("'{}' is too long ago", value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    /// A task with the given title and status, last edited days ago
+    fn task(title: &str, status: TaskStatus, days: i64) -> Task {
+        return Task::new(
+            "59ad73ec-efbd-4b37-ad53-02c8f3b17c56".to_string(),
+            status,
+            title.to_string(),
+            Utc::now() - Duration::days(days),
+            false,
+            BTreeMap::new(),
+        );
+    }
+
+    /// Whether the expression parses and matches the task
+    fn matches(expression: &str, task: &Task) -> bool {
+        return TaskFilter::from_str(expression).unwrap().matches(task);
+    }
+
+    #[test]
+    fn clauses_are_joined_with_and() {
+        let cat = task("Feed the cat", TaskStatus::Doing, 40);
+
+        assert!(matches("status!=done and title~CAT and age>30d", &cat));
+        assert!(!matches("status=doing AND age<30d", &cat));
+        assert!(matches(
+            "  title ~ feed   and  id=59ad73ecefbd4b37ad5302c8f3b17c56 ",
+            &cat
+        ));
+    }
+
+    #[test]
+    fn quoted_values_keep_and() {
+        let salt = task("Buy salt and pepper", TaskStatus::Todo, 1);
+
+        assert!(matches("title~\"salt and pepper\"", &salt));
+        assert!(matches("title~'salt and pepper' and status=todo", &salt));
+        assert!(!matches("title=\"salt and pepper\"", &salt));
+        assert!(!matches("title!~\"salt and pepper\"", &salt));
+    }
+
+    #[test]
+    fn invalid_expressions_are_rejected() {
+        for expression in [
+            "title cat",
+            "colour=red",
+            "status~do",
+            "status=later",
+            "age=3d",
+            "title~\"salt",
+            "title~cat and",
+            "title~cat and and status=todo",
+        ] {
+            assert!(
+                TaskFilter::from_str(expression).is_err(),
+                "{} should be rejected",
+                expression
+            );
+        }
+    }
+
+    #[test]
+    fn ages_parse_every_unit() {
+        assert_eq!(parse_age("45m").unwrap(), Duration::minutes(45));
+        assert_eq!(parse_age("12h").unwrap(), Duration::hours(12));
+        assert_eq!(parse_age(" 30d ").unwrap(), Duration::days(30));
+        assert_eq!(parse_age("2w").unwrap(), Duration::weeks(2));
+        assert_eq!(parse_age("9999999d").unwrap(), Duration::days(9_999_999));
+    }
+
+    #[test]
+    fn invalid_ages_are_rejected() {
+        for age in [
+            "30",
+            "d",
+            "30y",
+            "-3d",
+            "3.5d",
+            "",
+            "999999999999999d",
+            "99999999d",
+            "99999999999999999999w",
+        ] {
+            assert!(parse_age(age).is_err(), "{} should be rejected", age);
+        }
+    }
+}
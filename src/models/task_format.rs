@@ -0,0 +1,41 @@
+use std::{fmt::Display, path::Path};
+
+/// A file format tasks can be exported to and imported from
+#[derive(clap::ValueEnum, Clone, Debug, Copy, PartialEq, Eq)]
+pub enum TaskFormat {
+    /// Comma separated values, one row per task
+    Csv,
+    /// A JSON array of task objects
+    Json,
+    /// A markdown checklist grouped by status
+    Markdown,
+    /// One task per line in the todo.txt format
+    Todotxt,
+}
+
+impl TaskFormat {
+    /// Guess the format from a file extension
+    pub fn from_path(path: &Path) -> Option<TaskFormat> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+
+        return match extension.as_str() {
+            "csv" => Some(TaskFormat::Csv),
+            "json" => Some(TaskFormat::Json),
+            "md" | "markdown" => Some(TaskFormat::Markdown),
+            "txt" => Some(TaskFormat::Todotxt),
+            _ => None,
+        };
+    }
+}
+
+impl Display for TaskFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self {
+            TaskFormat::Csv => write!(f, "csv")?,
+            TaskFormat::Json => write!(f, "json")?,
+            TaskFormat::Markdown => write!(f, "markdown")?,
+            TaskFormat::Todotxt => write!(f, "todotxt")?,
+        };
+        return Ok(());
+    }
+}
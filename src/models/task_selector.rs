@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+
+use anyhow::{bail, Result};
+use clap::{ArgGroup, Args};
+
+use super::{task::Task, task_filter::TaskFilter, task_status::TaskStatus};
+
+/// Selects the tasks a command acts on
+/// IDs are checked first, then the status and filter, and finally the name
+#[derive(Args, Debug, Clone)]
+#[clap(group(ArgGroup::new("selector").required(true).multiple(true)))]
+pub struct TaskSelector {
+    /// Task IDs, unique ID prefixes of at least 4 characters, or numbers from the last `tasks list`
+    /// Pass - to read them from stdin, one per line
+    #[clap(group = "selector")]
+    pub ids: Vec<String>,
+    /// Select tasks by name, ranked by exact, prefix, substring and then fuzzy matches (case insensitive)
+    /// If several tasks match you'll be asked to pick one
+    #[clap(long, short, group = "selector")]
+    pub name: Option<String>,
+    /// Select tasks with this status
+    #[clap(long, short, value_enum, group = "selector")]
+    pub status: Option<TaskStatus>,
+    /// Select tasks matching a filter expression, e.g. "status!=done and title~cat and age>30d"
+    /// Fields: title, id, status and age. Operators: =, !=, ~ (contains), !~, < and > (age only)
+    /// Quote values with spaces, e.g. 'title~"salt and pepper"'
+    #[clap(long, short, group = "selector")]
+    pub filter: Option<TaskFilter>,
+    /// Act on every task whose title is, starts with or contains the name instead of picking one
+    #[clap(long, short, requires = "name")]
+    pub all: bool,
+}
+
+/// The shortest ID prefix accepted and shown, anything shorter made of digits is a listing index
+pub const MIN_PREFIX_LEN: usize = 4;
+
+/// Whether the value is an index into the last listing rather than an ID prefix
+pub fn is_index(value: &str) -> bool {
+    return !value.is_empty()
+        && value.len() < MIN_PREFIX_LEN
+        && value.chars().all(|c| c.is_ascii_digit());
+}
+
+/// Map each ID to the shortest prefix, of at least MIN_PREFIX_LEN characters, that no other ID starts with
+pub fn shortest_unique_prefixes(ids: &[&str]) -> HashMap<String, String> {
+    let mut stripped: Vec<String> = ids.iter().map(|id| id.replace('-', "")).collect();
+    stripped.sort();
+    stripped.dedup();
+
+    let common = |a: &str, b: &str| a.chars().zip(b.chars()).take_while(|(x, y)| x == y).count();
+
+    let mut prefixes = HashMap::with_capacity(ids.len());
+    for (i, id) in stripped.iter().enumerate() {
+        let before = i.checked_sub(1).map_or(0, |p| common(&stripped[p], id));
+        let after = stripped.get(i + 1).map_or(0, |n| common(n, id));
+        let len = (before.max(after) + 1).clamp(MIN_PREFIX_LEN, id.len());
+
+        prefixes.insert(id.clone(), id[..len].to_string());
+    }
+
+    return ids
+        .iter()
+        .map(|id| (id.to_string(), prefixes[&id.replace('-', "")].clone()))
+        .collect();
+}
+
+/// Whether the value is a complete task UUID, with or without dashes
+pub fn is_full_id(value: &str) -> bool {
+    let stripped = value.replace('-', "");
+
+    return stripped.len() == 32 && stripped.chars().all(|c| c.is_ascii_hexdigit());
+}
+
+/// Find the only task whose ID starts with prefix, dashes are ignored
+pub fn find_by_id_prefix<'a>(tasks: &'a [Task], prefix: &str) -> Result<&'a Task> {
+    let prefix = prefix.replace('-', "").to_lowercase();
+    if prefix.len() < MIN_PREFIX_LEN {
+        bail!(
+            "'{}' is too short, ID prefixes need at least {} characters",
+            prefix,
+            MIN_PREFIX_LEN
+        );
+    }
+
+    let matches: Vec<&Task> = tasks
+        .iter()
+        .filter(|t| t.id.replace('-', "").starts_with(&prefix))
+        .collect();
+
+    return match matches.as_slice() {
+        [] => bail!("No task with an ID starting with '{}' found", prefix),
+        [task] => Ok(task),
+        _ => bail!(
+            "'{}' is ambiguous, it matches {} tasks:\n{}",
+            prefix,
+            matches.len(),
+            matches
+                .iter()
+                .map(|t| format!("{} ({})", t.id, t.title))
+                .collect::<Vec<_>>()
+                .join("\n")
+        ),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+
+    /// A task with the given ID, titled after it
+    fn task(id: &str) -> Task {
+        return Task::new(
+            id.to_string(),
+            TaskStatus::Todo,
+            format!("Task {}", id),
+            Utc::now(),
+            false,
+            Default::default(),
+        );
+    }
+
+    #[test]
+    fn prefixes_are_unique_and_at_least_the_minimum() {
+        let ids = [
+            "59ad73ec-efbd-4b37-ad53-02c8f3b17c56",
+            "59ad7fff-0000-0000-0000-000000000000",
+            "8f21c0de-4b5a-4968-8776-a5b4c3d2e1f0",
+        ];
+        let prefixes = shortest_unique_prefixes(&ids);
+
+        assert_eq!(prefixes[ids[0]], "59ad73");
+        assert_eq!(prefixes[ids[1]], "59ad7f");
+        assert_eq!(prefixes[ids[2]], "8f21");
+    }
+
+    #[test]
+    fn prefixes_of_duplicate_ids_are_shared() {
+        let ids = [
+            "59ad73ec-efbd-4b37-ad53-02c8f3b17c56",
+            "59ad73ecefbd4b37ad5302c8f3b17c56",
+        ];
+        let prefixes = shortest_unique_prefixes(&ids);
+
+        assert_eq!(prefixes.len(), 2);
+        assert_eq!(prefixes[ids[0]], "59ad");
+        assert_eq!(prefixes[ids[1]], "59ad");
+    }
+
+    #[test]
+    fn prefix_finds_the_only_match() {
+        let tasks = [
+            task("59ad73ec-efbd-4b37-ad53-02c8f3b17c56"),
+            task("59ad7fff-0000-0000-0000-000000000000"),
+        ];
+
+        assert_eq!(
+            find_by_id_prefix(&tasks, "59AD-73").unwrap().id,
+            tasks[0].id
+        );
+        assert_eq!(
+            find_by_id_prefix(&tasks, "59ad7fff0000").unwrap().id,
+            tasks[1].id
+        );
+    }
+
+    #[test]
+    fn prefix_errors() {
+        let tasks = [
+            task("59ad73ec-efbd-4b37-ad53-02c8f3b17c56"),
+            task("59ad7fff-0000-0000-0000-000000000000"),
+        ];
+
+        let short = find_by_id_prefix(&tasks, "59a").unwrap_err().to_string();
+        assert!(short.contains("too short"), "{}", short);
+
+        let ambiguous = find_by_id_prefix(&tasks, "59ad7").unwrap_err().to_string();
+        assert!(ambiguous.contains("ambiguous"), "{}", ambiguous);
+
+        let missing = find_by_id_prefix(&tasks, "8f21").unwrap_err().to_string();
+        assert!(missing.contains("No task"), "{}", missing);
+    }
+
+    #[test]
+    fn indexes_and_full_ids() {
+        assert!(is_index("3"));
+        assert!(is_index("123"));
+        assert!(!is_index("1234"));
+        assert!(!is_index("5a"));
+        assert!(is_full_id("59ad73ec-efbd-4b37-ad53-02c8f3b17c56"));
+        assert!(is_full_id("59ad73ecefbd4b37ad5302c8f3b17c56"));
+        assert!(!is_full_id("59ad73ec"));
+    }
+}
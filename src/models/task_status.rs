@@ -1,7 +1,10 @@
 use std::fmt::Display;
 
+use serde::{Deserialize, Serialize};
+
 /// The current status of a Notion task
-#[derive(clap::ValueEnum, Clone, Debug, Copy)]
+#[derive(clap::ValueEnum, Clone, Debug, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum TaskStatus {
     /// Todo: not started
     Todo,
@@ -11,20 +14,6 @@ pub enum TaskStatus {
     Done,
 }
 
-impl TaskStatus {
-    /// Turn a status into a string representing itself
-    /// TaskStatus::Todo -> To Do
-    /// TaskStatus::Doing -> Doing
-    /// TaskStatus::Done -> Done
-    pub fn as_notion_status(&self) -> String {
-        return match self {
-            TaskStatus::Todo => "To Do".to_string(),
-            TaskStatus::Doing => "Doing".to_string(),
-            TaskStatus::Done => "Done".to_string(),
-        };
-    }
-}
-
 impl Display for TaskStatus {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match &self {
@@ -0,0 +1,76 @@
+//! Interactive prompts on the terminal and input piped to stdin
+
+use std::io::{self, BufRead, IsTerminal, Write};
+
+use anyhow::{bail, Result};
+
+/// Ask a yes/no question, anything other than y or yes is a no
+/// Fails when stdin isn't a terminal so scripts don't hang waiting for an answer
+pub fn confirm(question: &str) -> Result<bool> {
+    if !io::stdin().is_terminal() {
+        bail!(
+            "{} (not running interactively, pass --yes to skip this prompt)",
+            question
+        );
+    }
+
+    print!("{} [y/N] ", question);
+    io::stdout().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+
+    return Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"));
+}
+
+/// Ask for a value without echoing what is typed, e.g. a token
+pub fn secret(question: &str) -> Result<String> {
+    if !io::stdin().is_terminal() {
+        bail!("{} (not running interactively)", question);
+    }
+
+    return Ok(rpassword::prompt_password(format!("{}: ", question))?
+        .trim()
+        .to_string());
+}
+
+/// Read the non-empty lines piped to stdin, trimmed
+pub fn read_lines() -> Result<Vec<String>> {
+    let mut lines = Vec::new();
+
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        if !line.trim().is_empty() {
+            lines.push(line.trim().to_string());
+        }
+    }
+
+    return Ok(lines);
+}
+
+/// Ask the user to pick one of the options by number, returns the index of the chosen option
+pub fn pick(question: &str, options: &[String]) -> Result<usize> {
+    if !io::stdin().is_terminal() {
+        bail!("{} (not running interactively)", question);
+    }
+
+    println!("{}", question);
+    for (i, option) in options.iter().enumerate() {
+        println!("  {}) {}", i + 1, option);
+    }
+
+    loop {
+        print!("Pick a number [1-{}]: ", options.len());
+        io::stdout().flush()?;
+
+        let mut answer = String::new();
+        if io::stdin().lock().read_line(&mut answer)? == 0 {
+            bail!("no option picked");
+        }
+
+        match answer.trim().parse::<usize>() {
+            Ok(n) if (1..=options.len()).contains(&n) => return Ok(n - 1),
+            _ => println!("'{}' is not one of the options", answer.trim()),
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use std::fs::File;
+
+use anyhow::Result;
+
+use crate::{
+    models::cache::Cache,
+    services::config::{get_config_dir, write_config_file},
+    traits::CacheService,
+};
+
+/// Persists cached databases as json next to the config
+#[derive(Default)]
+pub struct JSONCacheService {}
+
+impl JSONCacheService {
+    /// Create a new JSONCacheService
+    pub fn new() -> JSONCacheService {
+        return JSONCacheService {};
+    }
+}
+
+impl CacheService for JSONCacheService {
+    fn get_cache(&self) -> Result<Cache> {
+        let cache_path = get_config_dir()?.join("cache.json");
+
+        if cache_path.exists() {
+            let file = File::open(cache_path)?;
+            let mut cache: Cache = serde_json::from_reader(file)?;
+            cache.upgrade();
+            return Ok(cache);
+        }
+
+        return Ok(Cache::default());
+    }
+
+    fn set_cache(&self, cache: &Cache) -> Result<()> {
+        write_config_file("cache.json", &serde_json::to_string(cache)?)?;
+
+        return Ok(());
+    }
+}
@@ -0,0 +1,571 @@
+use std::cell::Cell;
+
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+use colour::yellow_ln;
+use serde_json::{Map, Value};
+
+use crate::{
+    models::{
+        api::schema::Schema,
+        comment::Comment,
+        database::Database,
+        journal::{JournalEntry, Operation},
+        property_mapping::PropertyMapping,
+        task::{EditedTasks, NewTask, Task},
+        task_status::TaskStatus,
+    },
+    services::sync,
+    traits::{CacheService, JournalService, NotionCaller},
+};
+
+/// Wraps a Notion client and keeps a local copy of the tasks and schemas it reads
+/// In offline mode, or when Notion can't be reached, reads are answered from that copy
+/// and changes are queued in the journal until `notion sync`
+pub struct CachedNotion {
+    /// The client used when online
+    notion: Box<dyn NotionCaller>,
+    /// Where the local copy is kept
+    cache: Box<dyn CacheService>,
+    /// Where changes are queued
+    journal: Box<dyn JournalService>,
+    /// Whether to answer from the cache only
+    offline: bool,
+    /// Whether queued changes are only printed
+    dry_run: bool,
+    /// Set once a request fails to reach Notion, so later changes are queued right away
+    unreachable: Cell<bool>,
+    /// How many changes were queued so far
+    queued: Cell<usize>,
+    /// The database the client works on, recorded with queued changes so they are replayed with its mapping
+    database_id: Option<String>,
+}
+
+impl CachedNotion {
+    /// Wrap a Notion client with a cache and journal
+    /// database_id is the database the client's property mapping belongs to, if it belongs to one
+    pub fn new(
+        notion: Box<dyn NotionCaller>,
+        cache: Box<dyn CacheService>,
+        journal: Box<dyn JournalService>,
+        offline: bool,
+        dry_run: bool,
+        database_id: Option<String>,
+    ) -> CachedNotion {
+        return CachedNotion {
+            notion,
+            cache,
+            journal,
+            offline,
+            dry_run,
+            unreachable: Cell::new(false),
+            queued: Cell::new(0),
+            database_id,
+        };
+    }
+
+    /// Fetch from Notion, None means the caller should answer from the cache instead
+    fn read<T>(&self, fetch: impl FnOnce() -> Result<T>) -> Result<Option<T>> {
+        if self.offline || self.unreachable.get() {
+            return Ok(None);
+        }
+
+        return match fetch() {
+            Ok(value) => Ok(Some(value)),
+            Err(e) if is_unreachable(&e) => {
+                yellow_ln!("Notion couldn't be reached, using cached tasks");
+                self.unreachable.set(true);
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        };
+    }
+
+    /// Send a change to Notion, or queue it if Notion can't be reached
+    fn write(&self, operation: Operation, send: impl FnOnce() -> Result<()>) -> Result<()> {
+        return self.write_all(vec![operation], send);
+    }
+
+    /// Send several changes to Notion in one go, or queue each of them if Notion can't be reached
+    /// Changes sent before Notion became unreachable are queued again, so this is only for changes that can be repeated
+    fn write_all(
+        &self,
+        operations: Vec<Operation>,
+        send: impl FnOnce() -> Result<()>,
+    ) -> Result<()> {
+        if !self.offline && !self.unreachable.get() {
+            match send() {
+                Ok(()) => return Ok(()),
+                Err(e) if !is_unreachable(&e) => return Err(e),
+                Err(_) => {
+                    yellow_ln!("Notion couldn't be reached");
+                    self.unreachable.set(true);
+                }
+            }
+        }
+
+        return self.queue(operations);
+    }
+
+    /// Send several changes to Notion in one go, send returns whether each change was made in the same order
+    /// Only the changes that didn't reach Notion are queued, so this suits changes that mustn't be made twice
+    fn write_each(
+        &self,
+        operations: Vec<Operation>,
+        send: impl FnOnce() -> Result<Vec<Result<()>>>,
+    ) -> Result<Vec<Result<()>>> {
+        if self.offline || self.unreachable.get() {
+            let count = operations.len();
+            self.queue(operations)?;
+            return Ok((0..count).map(|_| Ok(())).collect());
+        }
+
+        let mut results = send()?;
+        let mut unsent = Vec::new();
+        for (operation, result) in operations.into_iter().zip(results.iter_mut()) {
+            if result.as_ref().is_err_and(is_unreachable) {
+                unsent.push(operation);
+                *result = Ok(());
+            }
+        }
+
+        if !unsent.is_empty() {
+            yellow_ln!("Notion couldn't be reached");
+            self.unreachable.set(true);
+            self.queue(unsent)?;
+        }
+
+        return Ok(results);
+    }
+
+    /// Queue changes in the journal until `notion sync`, and show them in the cached tasks
+    fn queue(&self, operations: Vec<Operation>) -> Result<()> {
+        let mut cache = self.cache.get_cache()?;
+        let entries: Vec<JournalEntry> = operations
+            .into_iter()
+            .map(|operation| JournalEntry {
+                queued_at: Utc::now(),
+                base_edited_time: operation
+                    .task_id()
+                    .and_then(|id| cache.find_task(id))
+                    .map(|t| t.last_edited_time),
+                database_id: self.database_id.clone(),
+                operation,
+            })
+            .collect();
+
+        self.queued.set(self.queued.get() + entries.len());
+
+        if self.dry_run {
+            for entry in &entries {
+                println!("Would queue: {}", serde_json::to_string_pretty(entry)?);
+            }
+            return Ok(());
+        }
+
+        // the cached copy shows the changes right away, the next pull replaces it with Notion's
+        for entry in &entries {
+            cache.apply(&entry.operation);
+        }
+        self.cache.set_cache(&cache)?;
+
+        let mut journal = self.journal.get_journal()?;
+        let descriptions: Vec<String> = entries.iter().map(|e| e.operation.describe()).collect();
+        journal.entries.extend(entries);
+        self.journal.set_journal(&journal)?;
+
+        for description in descriptions {
+            yellow_ln!(
+                "Queued: {}, run `notion sync` to send it to Notion",
+                description
+            );
+        }
+
+        return Ok(());
+    }
+
+    /// Fail with a helpful message when an operation needs the network
+    fn ensure_online(&self, operation: &str) -> Result<()> {
+        if self.offline {
+            bail!(
+                "can't {} while offline, try again without --offline",
+                operation
+            );
+        }
+
+        return Ok(());
+    }
+}
+
+impl NotionCaller for CachedNotion {
+    fn list_tasks(&self, database_id: &str, status: &Option<TaskStatus>) -> Result<Vec<Task>> {
+        let mut cache = self.cache.get_cache()?;
+        let db = cache.databases.entry(database_id.to_string()).or_default();
+
+        // only tasks edited since the last pull are fetched, the rest are already cached
+        let pulled = self.read(|| sync::pull(self.notion.as_ref(), database_id, db, false))?;
+
+        if pulled.is_none() && db.sync_cursor.is_none() && db.refreshed_at.is_none() {
+            bail!("no cached tasks for this database, run `notion tasks list` while online first");
+        }
+
+        let tasks = db
+            .tasks
+            .values()
+            .filter(|t| status.is_none_or(|s| t.status == s))
+            .cloned()
+            .collect();
+
+        if pulled.is_some() {
+            if db.schema.is_none() {
+                db.schema = Some(self.notion.get_database_schema(database_id)?);
+            }
+            self.cache.set_cache(&cache)?;
+        }
+
+        return Ok(tasks);
+    }
+
+    fn list_tasks_edited_since(
+        &self,
+        database_id: &str,
+        since: &DateTime<Utc>,
+    ) -> Result<EditedTasks> {
+        self.ensure_online("fetch changed tasks")?;
+        return self.notion.list_tasks_edited_since(database_id, since);
+    }
+
+    fn get_task(&self, id: &str) -> Result<Task> {
+        if let Some(task) = self.read(|| self.notion.get_task(id))? {
+            return Ok(task);
+        }
+
+        return match self.cache.get_cache()?.find_task(id) {
+            Some(task) => Ok(task.clone()),
+            None => bail!("task {} isn't cached", id),
+        };
+    }
+
+    fn get_task_body(&self, id: &str) -> Result<Vec<Value>> {
+        self.ensure_online("read task content")?;
+        return self.notion.get_task_body(id);
+    }
+
+    fn get_database_schema(&self, database_id: &str) -> Result<Schema> {
+        let fetched = self.read(|| self.notion.get_database_schema(database_id))?;
+        let mut cache = self.cache.get_cache()?;
+        let db = cache.databases.entry(database_id.to_string()).or_default();
+
+        let Some(schema) = fetched else {
+            return match &db.schema {
+                Some(schema) => Ok(schema.clone()),
+                None => bail!("the database schema isn't cached"),
+            };
+        };
+
+        db.schema = Some(schema.clone());
+        self.cache.set_cache(&cache)?;
+
+        return Ok(schema);
+    }
+
+    fn add_task(
+        &self,
+        database_id: &str,
+        title: &str,
+        status: &TaskStatus,
+        body: &[Value],
+        properties: &Map<String, Value>,
+    ) -> Result<()> {
+        let operation = Operation::AddTask {
+            database_id: database_id.to_string(),
+            title: title.to_string(),
+            status: *status,
+            body: body.to_vec(),
+            properties: properties.clone(),
+        };
+
+        return self.write(operation, || {
+            self.notion
+                .add_task(database_id, title, status, body, properties)
+        });
+    }
+
+    fn add_tasks(&self, database_id: &str, tasks: &[NewTask]) -> Result<Vec<Result<()>>> {
+        let operations = tasks
+            .iter()
+            .map(|task| Operation::AddTask {
+                database_id: database_id.to_string(),
+                title: task.title.clone(),
+                status: task.status,
+                body: task.body.clone(),
+                properties: task.properties.clone(),
+            })
+            .collect();
+
+        return self.write_each(operations, || self.notion.add_tasks(database_id, tasks));
+    }
+
+    fn get_database_title(&self, database_id: &str) -> Result<String> {
+        self.ensure_online("look up a database")?;
+        return self.notion.get_database_title(database_id);
+    }
+
+    fn list_eligible_databases(&self) -> Result<Vec<Database>> {
+        self.ensure_online("search for databases")?;
+        return self.notion.list_eligible_databases();
+    }
+
+    fn create_database(
+        &self,
+        parent_page_id: &str,
+        title: &str,
+        mapping: &PropertyMapping,
+    ) -> Result<Option<Database>> {
+        self.ensure_online("create a database")?;
+        return self.notion.create_database(parent_page_id, title, mapping);
+    }
+
+    fn mark_as_done(&self, ids: &[String]) -> Result<()> {
+        // the tasks are sent together so the client can mark them in parallel, and queued one by one
+        let operations = ids
+            .iter()
+            .map(|id| Operation::MarkAsDone { id: id.clone() })
+            .collect();
+
+        return self.write_all(operations, || self.notion.mark_as_done(ids));
+    }
+
+    fn update_task(&self, id: &str, to: &Option<TaskStatus>, name: &Option<String>) -> Result<()> {
+        return self.update_tasks(&[id.to_string()], to, name);
+    }
+
+    fn update_tasks(
+        &self,
+        ids: &[String],
+        to: &Option<TaskStatus>,
+        name: &Option<String>,
+    ) -> Result<()> {
+        let operations = ids
+            .iter()
+            .map(|id| Operation::UpdateTask {
+                id: id.clone(),
+                to: *to,
+                name: name.clone(),
+            })
+            .collect();
+
+        return self.write_all(operations, || self.notion.update_tasks(ids, to, name));
+    }
+
+    fn archive_task(&self, id: &str) -> Result<()> {
+        return self.archive_tasks(&[id.to_string()]);
+    }
+
+    fn archive_tasks(&self, ids: &[String]) -> Result<()> {
+        let operations = ids
+            .iter()
+            .map(|id| Operation::ArchiveTask { id: id.clone() })
+            .collect();
+
+        return self.write_all(operations, || {
+            self.notion.archive_tasks(ids)?;
+
+            // archived pages aren't returned by incremental pulls, so drop the tasks here
+            if !self.dry_run {
+                let mut cache = self.cache.get_cache()?;
+                for db in cache.databases.values_mut() {
+                    for id in ids {
+                        db.tasks.remove(id);
+                    }
+                }
+                self.cache.set_cache(&cache)?;
+            }
+
+            return Ok(());
+        });
+    }
+
+    fn restore_task(&self, id: &str) -> Result<()> {
+        let operation = Operation::RestoreTask { id: id.to_string() };
+        return self.write(operation, || self.notion.restore_task(id));
+    }
+
+    fn add_comment(&self, id: &str, text: &str) -> Result<()> {
+        let operation = Operation::AddComment {
+            id: id.to_string(),
+            text: text.to_string(),
+        };
+
+        return self.write(operation, || self.notion.add_comment(id, text));
+    }
+
+    fn list_comments(&self, id: &str) -> Result<Vec<Comment>> {
+        self.ensure_online("read comments")?;
+        return self.notion.list_comments(id);
+    }
+
+    fn get_current_user(&self) -> Result<String> {
+        self.ensure_online("check the token")?;
+        return self.notion.get_current_user();
+    }
+
+    fn queued_changes(&self) -> usize {
+        return self.queued.get();
+    }
+}
+
+/// Whether a request failed because Notion couldn't be reached at all
+pub fn is_unreachable(error: &anyhow::Error) -> bool {
+    return error.chain().any(|cause| {
+        cause
+            .downcast_ref::<reqwest::Error>()
+            .is_some_and(|e| e.is_connect() || e.is_timeout())
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        models::cache::Cache,
+        services::fake::{task, time, FakeNotion, MemoryCache, MemoryJournal},
+    };
+
+    /// A client for the database db, whose cache holds tasks 1 and 2 edited at minute 0
+    fn cached(
+        offline: bool,
+        dry_run: bool,
+    ) -> (CachedNotion, FakeNotion, MemoryCache, MemoryJournal) {
+        let notion = FakeNotion::with_tasks(vec![
+            task("1", "One", TaskStatus::Todo, 0),
+            task("2", "Two", TaskStatus::Todo, 0),
+        ]);
+        let cache = MemoryCache::default();
+        let journal = MemoryJournal::default();
+
+        let mut local = Cache::default();
+        let db = local.databases.entry("db".to_string()).or_default();
+        db.refreshed_at = Some(time(0));
+        for task in notion.list_tasks("db", &None).unwrap() {
+            db.tasks.insert(task.id.clone(), task);
+        }
+        cache.set_cache(&local).unwrap();
+
+        let client = CachedNotion::new(
+            Box::new(notion.clone()),
+            Box::new(cache.clone()),
+            Box::new(journal.clone()),
+            offline,
+            dry_run,
+            Some("db".to_string()),
+        );
+
+        return (client, notion, cache, journal);
+    }
+
+    #[test]
+    fn online_changes_are_sent() {
+        let (client, notion, _, journal) = cached(false, false);
+
+        client.mark_as_done(&["1".to_string()]).unwrap();
+
+        assert_eq!(notion.calls(), vec!["done 1"]);
+        assert!(journal.get_journal().unwrap().entries.is_empty());
+        assert_eq!(client.queued_changes(), 0);
+    }
+
+    #[test]
+    fn offline_changes_are_queued_one_entry_each() {
+        let (client, notion, _, journal) = cached(true, false);
+
+        client
+            .mark_as_done(&["1".to_string(), "2".to_string()])
+            .unwrap();
+
+        assert!(notion.calls().is_empty());
+        assert_eq!(client.queued_changes(), 2);
+        let entries = journal.get_journal().unwrap().entries;
+        assert_eq!(entries.len(), 2);
+        for entry in &entries {
+            assert_eq!(entry.base_edited_time, Some(time(0)));
+            assert_eq!(entry.database_id.as_deref(), Some("db"));
+        }
+        assert_eq!(entries[1].operation.describe(), "mark 2 as done");
+    }
+
+    #[test]
+    fn queued_changes_show_in_the_cache() {
+        let (client, _, cache, _) = cached(true, false);
+
+        client
+            .update_task("1", &Some(TaskStatus::Doing), &Some("Renamed".to_string()))
+            .unwrap();
+        client.archive_task("2").unwrap();
+
+        let tasks = client.list_tasks("db", &None).unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].title, "Renamed");
+        assert_eq!(tasks[0].status, TaskStatus::Doing);
+        // the edit time is Notion's, so the next pull replaces the copy
+        assert_eq!(
+            cache.get_cache().unwrap().databases["db"].tasks["1"].last_edited_time,
+            time(0)
+        );
+    }
+
+    fn new_task(title: &str) -> NewTask {
+        return NewTask {
+            title: title.to_string(),
+            status: TaskStatus::Todo,
+            body: Vec::new(),
+            properties: Map::new(),
+        };
+    }
+
+    #[test]
+    fn added_tasks_report_each_result() {
+        let (client, notion, _, journal) = cached(false, false);
+        notion.0.borrow_mut().failing.push("Broken".to_string());
+
+        let results = client
+            .add_tasks("db", &[new_task("Fine"), new_task("Broken")])
+            .unwrap();
+
+        assert!(results[0].is_ok());
+        assert!(results[1].is_err());
+        assert_eq!(notion.calls(), vec!["add Fine"]);
+        assert!(journal.get_journal().unwrap().entries.is_empty());
+    }
+
+    #[test]
+    fn offline_tasks_are_added_to_the_journal() {
+        let (client, notion, _, journal) = cached(true, false);
+
+        let results = client
+            .add_tasks("db", &[new_task("One"), new_task("Two")])
+            .unwrap();
+
+        assert!(results.iter().all(Result::is_ok));
+        assert!(notion.calls().is_empty());
+        let queued: Vec<String> = journal
+            .get_journal()
+            .unwrap()
+            .entries
+            .iter()
+            .map(|e| e.operation.describe())
+            .collect();
+        assert_eq!(queued, vec!["add task 'One'", "add task 'Two'"]);
+    }
+
+    #[test]
+    fn dry_run_queues_nothing() {
+        let (client, _, cache, journal) = cached(true, true);
+        let before = cache.0.borrow().clone();
+
+        client.archive_task("1").unwrap();
+
+        assert!(journal.get_journal().unwrap().entries.is_empty());
+        assert_eq!(*cache.0.borrow(), before);
+    }
+}
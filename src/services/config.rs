@@ -7,29 +7,50 @@ use std::{
 use crate::{models::config::AppConfig, traits::ConfigService};
 use anyhow::{bail, Result};
 
+/// Persists the config as json in ~/.notion-cli/config.json
+#[derive(Default)]
 pub struct JSONConfigService {}
 
 impl JSONConfigService {
+    /// Create a new JSONConfigService
     pub fn new() -> JSONConfigService {
         JSONConfigService {}
     }
+}
 
-    fn get_config_dir(&self) -> Result<PathBuf> {
-        let mut config_dir = match dirs::home_dir() {
-            Some(dir) => dir,
-            None => {
-                bail!("failed to get home_dir for this platform");
-            }
-        };
-        config_dir.push(".notion-cli");
+/// The directory the app keeps its files in, ~/.notion-cli
+pub fn get_config_dir() -> Result<PathBuf> {
+    let mut config_dir = match dirs::home_dir() {
+        Some(dir) => dir,
+        None => {
+            bail!("failed to get home_dir for this platform");
+        }
+    };
+    config_dir.push(".notion-cli");
 
-        return Ok(config_dir);
-    }
+    return Ok(config_dir);
+}
+
+/// Replace a file in the config directory with contents
+/// The contents are written to a temporary file first and renamed over the old file,
+/// so an interrupted write never leaves a half written file behind
+pub fn write_config_file(name: &str, contents: &str) -> Result<()> {
+    let config_dir = get_config_dir()?;
+    fs::create_dir_all(&config_dir)?;
+
+    let temp_path = config_dir.join(format!(".{}.tmp", name));
+    let mut file = File::create(&temp_path)?;
+    file.write_all(contents.as_bytes())?;
+    file.sync_all()?;
+
+    fs::rename(temp_path, config_dir.join(name))?;
+
+    return Ok(());
 }
 
 impl ConfigService for JSONConfigService {
     fn get_config(&self) -> Result<AppConfig> {
-        let config_dir = self.get_config_dir()?;
+        let config_dir = get_config_dir()?;
         let config_path = config_dir.join("config.json");
 
         let exists = Path::new(&config_path).exists();
@@ -42,14 +63,13 @@ impl ConfigService for JSONConfigService {
         return Ok(AppConfig::default());
     }
 
-    fn set_config(&self, config: AppConfig) -> Result<()> {
-        let config_dir = self.get_config_dir()?;
-        let config_path = config_dir.join("config.json");
+    fn exists(&self) -> Result<bool> {
+        return Ok(get_config_dir()?.join("config.json").exists());
+    }
 
-        fs::create_dir_all(config_dir)?;
-        let mut file = File::create(config_path)?;
+    fn set_config(&self, config: AppConfig) -> Result<()> {
         let json_str = serde_json::to_string_pretty(&config)?;
-        file.write_all(json_str.as_bytes())?;
+        write_config_file("config.json", &json_str)?;
 
         return Ok(());
     }
@@ -0,0 +1,328 @@
+use std::{cell::RefCell, collections::BTreeMap, rc::Rc};
+
+use anyhow::{bail, Result};
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use serde_json::{Map, Value};
+
+use crate::{
+    models::{
+        api::schema::Schema,
+        cache::Cache,
+        comment::Comment,
+        config::AppConfig,
+        database::Database,
+        journal::Journal,
+        property_mapping::PropertyMapping,
+        task::{EditedTasks, NewTask, Task},
+        task_status::TaskStatus,
+    },
+    traits::{CacheService, ConfigService, JournalService, NotionCaller},
+};
+
+/// A point in time tests count from
+pub fn time(minutes: i64) -> DateTime<Utc> {
+    return Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap() + Duration::minutes(minutes);
+}
+
+/// A task last edited the given number of minutes after the start of the tests' time
+pub fn task(id: &str, title: &str, status: TaskStatus, minutes: i64) -> Task {
+    return Task::new(
+        id.to_string(),
+        status,
+        title.to_string(),
+        time(minutes),
+        false,
+        BTreeMap::new(),
+    );
+}
+
+/// What the fake Notion holds, shared with the test that set it up
+#[derive(Default)]
+pub struct NotionState {
+    /// The pages that are tasks, by ID
+    pub tasks: BTreeMap<String, Task>,
+    /// IDs of pages that were edited into something that isn't a task
+    pub not_tasks: Vec<String>,
+    /// Every change made, in order, e.g. "done 1"
+    pub calls: Vec<String>,
+    /// Changes to tasks with these IDs, adding tasks with these titles and reading the schema of
+    /// databases with these IDs fail
+    pub failing: Vec<String>,
+    /// Database schemas by database ID, databases not in it have no properties
+    pub schemas: BTreeMap<String, Schema>,
+}
+
+/// A NotionCaller that keeps its tasks in memory and records the changes made to them
+/// Clones share their state
+#[derive(Clone, Default)]
+pub struct FakeNotion(pub Rc<RefCell<NotionState>>);
+
+impl FakeNotion {
+    /// A fake Notion holding these tasks
+    pub fn with_tasks(tasks: Vec<Task>) -> FakeNotion {
+        let notion = FakeNotion::default();
+        notion.0.borrow_mut().tasks = tasks.into_iter().map(|t| (t.id.clone(), t)).collect();
+
+        return notion;
+    }
+
+    /// The changes made so far
+    pub fn calls(&self) -> Vec<String> {
+        return self.0.borrow().calls.clone();
+    }
+
+    /// Record a change to a task and mark it as edited now
+    fn change(&self, id: &str, call: String, edit: impl FnOnce(&mut Task)) -> Result<()> {
+        let mut state = self.0.borrow_mut();
+        if state.failing.iter().any(|f| f == id) {
+            bail!("changing {} failed", id);
+        }
+
+        let Some(task) = state.tasks.get_mut(id) else {
+            bail!("no task {}", id);
+        };
+        edit(task);
+        task.last_edited_time = Utc::now();
+        state.calls.push(call);
+
+        return Ok(());
+    }
+}
+
+impl NotionCaller for FakeNotion {
+    fn list_tasks(&self, _database_id: &str, status: &Option<TaskStatus>) -> Result<Vec<Task>> {
+        return Ok(self
+            .0
+            .borrow()
+            .tasks
+            .values()
+            .filter(|t| !t.archived && status.is_none_or(|s| t.status == s))
+            .cloned()
+            .collect());
+    }
+
+    fn list_tasks_edited_since(
+        &self,
+        _database_id: &str,
+        since: &DateTime<Utc>,
+    ) -> Result<EditedTasks> {
+        let state = self.0.borrow();
+
+        return Ok(EditedTasks {
+            tasks: state
+                .tasks
+                .values()
+                .filter(|t| !t.archived && t.last_edited_time >= *since)
+                .cloned()
+                .collect(),
+            not_tasks: state.not_tasks.clone(),
+        });
+    }
+
+    fn get_task(&self, id: &str) -> Result<Task> {
+        return match self.0.borrow().tasks.get(id) {
+            Some(task) => Ok(task.clone()),
+            None => bail!("no task {}", id),
+        };
+    }
+
+    fn get_task_body(&self, _id: &str) -> Result<Vec<Value>> {
+        return Ok(Vec::new());
+    }
+
+    fn get_database_schema(&self, database_id: &str) -> Result<Schema> {
+        let state = self.0.borrow();
+        if state.failing.iter().any(|f| f == database_id) {
+            bail!("no access to database {}", database_id);
+        }
+
+        return Ok(state.schemas.get(database_id).cloned().unwrap_or_default());
+    }
+
+    fn get_database_title(&self, _database_id: &str) -> Result<String> {
+        return Ok("Tasks".to_string());
+    }
+
+    fn add_task(
+        &self,
+        _database_id: &str,
+        title: &str,
+        status: &TaskStatus,
+        _body: &[Value],
+        _properties: &Map<String, Value>,
+    ) -> Result<()> {
+        let mut state = self.0.borrow_mut();
+        let id = format!("new{}", state.tasks.len());
+        let mut task = task(&id, title, *status, 0);
+        task.last_edited_time = Utc::now();
+        state.tasks.insert(id, task);
+        state.calls.push(format!("add {}", title));
+
+        return Ok(());
+    }
+
+    fn add_tasks(&self, database_id: &str, tasks: &[NewTask]) -> Result<Vec<Result<()>>> {
+        return Ok(tasks
+            .iter()
+            .map(|task| {
+                if self.0.borrow().failing.contains(&task.title) {
+                    bail!("adding {} failed", task.title);
+                }
+                self.add_task(
+                    database_id,
+                    &task.title,
+                    &task.status,
+                    &task.body,
+                    &task.properties,
+                )
+            })
+            .collect());
+    }
+
+    fn list_eligible_databases(&self) -> Result<Vec<Database>> {
+        return Ok(Vec::new());
+    }
+
+    fn create_database(
+        &self,
+        _parent_page_id: &str,
+        _title: &str,
+        _mapping: &PropertyMapping,
+    ) -> Result<Option<Database>> {
+        bail!("creating databases isn't faked");
+    }
+
+    fn mark_as_done(&self, ids: &[String]) -> Result<()> {
+        for id in ids {
+            self.change(id, format!("done {}", id), |t| t.status = TaskStatus::Done)?;
+        }
+
+        return Ok(());
+    }
+
+    fn update_task(&self, id: &str, to: &Option<TaskStatus>, name: &Option<String>) -> Result<()> {
+        return self.change(id, format!("update {}", id), |t| {
+            if let Some(status) = to {
+                t.status = *status;
+            }
+            if let Some(name) = name {
+                t.title = name.clone();
+            }
+        });
+    }
+
+    fn update_tasks(
+        &self,
+        ids: &[String],
+        to: &Option<TaskStatus>,
+        name: &Option<String>,
+    ) -> Result<()> {
+        for id in ids {
+            self.update_task(id, to, name)?;
+        }
+
+        return Ok(());
+    }
+
+    fn archive_task(&self, id: &str) -> Result<()> {
+        return self.change(id, format!("archive {}", id), |t| t.archived = true);
+    }
+
+    fn archive_tasks(&self, ids: &[String]) -> Result<()> {
+        for id in ids {
+            self.archive_task(id)?;
+        }
+
+        return Ok(());
+    }
+
+    fn restore_task(&self, id: &str) -> Result<()> {
+        return self.change(id, format!("restore {}", id), |t| t.archived = false);
+    }
+
+    fn add_comment(&self, id: &str, text: &str) -> Result<()> {
+        return self.change(id, format!("comment {} {}", id, text), |_| {});
+    }
+
+    fn list_comments(&self, _id: &str) -> Result<Vec<Comment>> {
+        return Ok(Vec::new());
+    }
+
+    fn get_current_user(&self) -> Result<String> {
+        return Ok("Test integration".to_string());
+    }
+}
+
+/// A CacheService that keeps the cache in memory, clones share it
+#[derive(Clone, Default)]
+pub struct MemoryCache(pub Rc<RefCell<String>>);
+
+impl CacheService for MemoryCache {
+    fn get_cache(&self) -> Result<Cache> {
+        let json = self.0.borrow();
+        if json.is_empty() {
+            return Ok(Cache::default());
+        }
+
+        return Ok(serde_json::from_str(&json)?);
+    }
+
+    fn set_cache(&self, cache: &Cache) -> Result<()> {
+        *self.0.borrow_mut() = serde_json::to_string(cache)?;
+        return Ok(());
+    }
+}
+
+/// A ConfigService that keeps the config in memory, clones share it
+#[derive(Clone, Default)]
+pub struct MemoryConfig(pub Rc<RefCell<String>>);
+
+impl MemoryConfig {
+    /// A saved config
+    pub fn with_config(config: AppConfig) -> MemoryConfig {
+        let memory = MemoryConfig::default();
+        memory.set_config(config).unwrap();
+
+        return memory;
+    }
+}
+
+impl ConfigService for MemoryConfig {
+    fn get_config(&self) -> Result<AppConfig> {
+        let json = self.0.borrow();
+        if json.is_empty() {
+            return Ok(AppConfig::default());
+        }
+
+        return Ok(serde_json::from_str(&json)?);
+    }
+
+    fn set_config(&self, config: AppConfig) -> Result<()> {
+        *self.0.borrow_mut() = serde_json::to_string(&config)?;
+        return Ok(());
+    }
+
+    fn exists(&self) -> Result<bool> {
+        return Ok(!self.0.borrow().is_empty());
+    }
+}
+
+/// A JournalService that keeps the journal in memory, clones share it
+#[derive(Clone, Default)]
+pub struct MemoryJournal(pub Rc<RefCell<String>>);
+
+impl JournalService for MemoryJournal {
+    fn get_journal(&self) -> Result<Journal> {
+        let json = self.0.borrow();
+        if json.is_empty() {
+            return Ok(Journal::default());
+        }
+
+        return Ok(serde_json::from_str(&json)?);
+    }
+
+    fn set_journal(&self, journal: &Journal) -> Result<()> {
+        *self.0.borrow_mut() = serde_json::to_string(journal)?;
+        return Ok(());
+    }
+}
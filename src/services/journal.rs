@@ -0,0 +1,40 @@
+use std::fs::File;
+
+use anyhow::Result;
+
+use crate::{
+    models::journal::Journal,
+    services::config::{get_config_dir, write_config_file},
+    traits::JournalService,
+};
+
+/// Persists queued changes as json next to the config
+#[derive(Default)]
+pub struct JSONJournalService {}
+
+impl JSONJournalService {
+    /// Create a new JSONJournalService
+    pub fn new() -> JSONJournalService {
+        return JSONJournalService {};
+    }
+}
+
+impl JournalService for JSONJournalService {
+    fn get_journal(&self) -> Result<Journal> {
+        let journal_path = get_config_dir()?.join("journal.json");
+
+        if journal_path.exists() {
+            let file = File::open(journal_path)?;
+            let journal: Journal = serde_json::from_reader(file)?;
+            return Ok(journal);
+        }
+
+        return Ok(Journal::default());
+    }
+
+    fn set_journal(&self, journal: &Journal) -> Result<()> {
+        write_config_file("journal.json", &serde_json::to_string_pretty(journal)?)?;
+
+        return Ok(());
+    }
+}
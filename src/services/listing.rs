@@ -0,0 +1,40 @@
+use std::fs::File;
+
+use anyhow::Result;
+
+use crate::{
+    models::listing::Listing,
+    services::config::{get_config_dir, write_config_file},
+    traits::ListingService,
+};
+
+/// Persists the last task listing as json next to the config
+#[derive(Default)]
+pub struct JSONListingService {}
+
+impl JSONListingService {
+    /// Create a new JSONListingService
+    pub fn new() -> JSONListingService {
+        return JSONListingService {};
+    }
+}
+
+impl ListingService for JSONListingService {
+    fn get_listing(&self) -> Result<Listing> {
+        let listing_path = get_config_dir()?.join("last_list.json");
+
+        if listing_path.exists() {
+            let file = File::open(listing_path)?;
+            let listing: Listing = serde_json::from_reader(file)?;
+            return Ok(listing);
+        }
+
+        return Ok(Listing::default());
+    }
+
+    fn set_listing(&self, listing: Listing) -> Result<()> {
+        write_config_file("last_list.json", &serde_json::to_string_pretty(&listing)?)?;
+
+        return Ok(());
+    }
+}
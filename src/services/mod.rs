@@ -1,2 +1,17 @@
+/// Stores the local copy of databases in ~/.notion-cli
+pub mod cache;
+/// A NotionCaller that falls back to the local copy when Notion can't be reached
+pub mod cached_notion;
+/// Stores the app config in ~/.notion-cli
 pub mod config;
+/// In-memory stand-ins for Notion and the files in ~/.notion-cli, for tests
+#[cfg(test)]
+pub(crate) mod fake;
+/// Stores changes queued while offline in ~/.notion-cli
+pub mod journal;
+/// Stores the last task listing in ~/.notion-cli
+pub mod listing;
+/// The Notion API client, async and blocking
 pub mod notion;
+/// Pulls tasks edited in Notion into the local copy
+pub mod sync;
@@ -1,182 +1,489 @@
-use std::{collections::HashSet, str::FromStr};
+use std::{collections::HashMap, future::Future, str::FromStr, time::Duration};
 
 use anyhow::{bail, Result};
-use reqwest::{blocking::Client, header::HeaderMap, Url};
-use serde::Deserialize;
-use serde_json::{json, Value};
+use chrono::{DateTime, Utc};
+use colour::e_yellow_ln;
+use futures::{stream, StreamExt, TryStreamExt};
+use reqwest::{
+    header::{HeaderMap, RETRY_AFTER},
+    Client, Method, RequestBuilder, Response, StatusCode, Url,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use tokio::{runtime::Runtime, sync::Semaphore};
 
 use crate::{
-    models::{database::Database, task::Task, task_status::TaskStatus},
+    markdown,
+    models::{
+        api::{
+            block::Block,
+            database::DatabaseObject,
+            filter::{DateCondition, Filter, TimestampFilter},
+            page::Page,
+            property::PropertyValue,
+            rich_text::{self, RichText},
+            schema::Schema,
+            sort::Sort,
+            user::{PartialUser, User},
+        },
+        comment::Comment,
+        database::Database,
+        property_mapping::PropertyMapping,
+        task::{EditedTasks, NewTask, Task},
+        task_status::TaskStatus,
+    },
     traits,
 };
 
 /// The notion version this app was built to work with
-const NOTION_VERSION: &str = "2022-02-22";
+pub const NOTION_VERSION: &str = "2022-02-22";
 
-/// Notion API wrapper
-pub struct NotionAPI {
+/// How many requests a client has in flight at once, however many run in parallel
+/// Notion allows an average of three requests per second per integration
+const MAX_CONCURRENT_REQUESTS: usize = 3;
+
+/// How many times a rate limited request is retried before its 429 response is returned
+const MAX_RETRIES: usize = 3;
+
+/// How long to wait before retrying a rate limited request when Notion doesn't say
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(1);
+
+/// The most blocks Notion accepts in a single children array
+const MAX_CHILDREN: usize = 100;
+
+/// How many levels of children Notion accepts below the blocks sent in one request
+const MAX_NESTING: usize = 2;
+
+/// Stands in for the IDs of pages and blocks that aren't created in dry run mode
+const DRY_RUN_ID: &str = "00000000-0000-0000-0000-000000000000";
+
+/// Async Notion API client, runs on tokio
+/// Independent requests, like nested blocks or changes to several tasks, are sent in parallel
+pub struct NotionClient {
     /// Notion's base url
     base_url: Url,
     /// Notion integration token
     token: String,
     /// HTTP client
     client: Client,
+    /// Which properties hold the task title and status
+    mapping: PropertyMapping,
+    /// When set, mutating requests are printed instead of sent
+    dry_run: bool,
+    /// Shared by every request, so parallel and nested fetches together stay under MAX_CONCURRENT_REQUESTS
+    limiter: Semaphore,
 }
 
-impl NotionAPI {
-    /// Construct a new Notion object provided a base_url and token
+impl NotionClient {
+    /// Construct a new Notion client provided a base_url, token and the database's property mapping
+    /// With dry_run set, requests that would modify the workspace are printed instead of sent
     /// Can panic if: TLS backend cannot be initialized, or the resolver cannot load the system configuration
-    pub fn new(base_url: String, token: String) -> Result<NotionAPI> {
+    pub fn new(
+        base_url: String,
+        token: String,
+        mapping: PropertyMapping,
+        dry_run: bool,
+    ) -> Result<NotionClient> {
         let mut headers = HeaderMap::new();
         headers.insert("Notion-Version", NOTION_VERSION.parse()?);
 
-        let client = reqwest::blocking::ClientBuilder::new()
+        let client = reqwest::ClientBuilder::new()
             .https_only(true)
             .default_headers(headers)
             .build()?;
 
         let base_url = Url::from_str(&base_url)?;
 
-        return Ok(NotionAPI {
+        return Ok(NotionClient {
             base_url,
             client,
             token,
+            mapping,
+            dry_run,
+            limiter: Semaphore::new(MAX_CONCURRENT_REQUESTS),
         });
     }
 }
 
+/// Blocking Notion API wrapper for the CLI, runs each operation of a NotionClient to completion
+/// It drives its own runtime, so from async code use NotionClient instead: calls made inside a tokio runtime fail,
+/// and like any tokio runtime it panics if dropped there
+pub struct NotionAPI {
+    /// The async client doing the work
+    client: NotionClient,
+    /// Runtime the client's requests are driven on
+    runtime: Runtime,
+}
+
+impl NotionAPI {
+    /// Construct a new Notion object provided a base_url, token and the database's property mapping
+    /// With dry_run set, requests that would modify the workspace are printed instead of sent
+    /// Can panic if: TLS backend cannot be initialized, or the resolver cannot load the system configuration
+    pub fn new(
+        base_url: String,
+        token: String,
+        mapping: PropertyMapping,
+        dry_run: bool,
+    ) -> Result<NotionAPI> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+
+        let client = NotionClient::new(base_url, token, mapping, dry_run)?;
+
+        return Ok(NotionAPI { client, runtime });
+    }
+
+    /// Run an operation of the client to completion
+    /// Blocking inside another runtime would panic, so that is reported as an error instead
+    fn run<T>(&self, operation: impl Future<Output = Result<T>>) -> Result<T> {
+        if tokio::runtime::Handle::try_current().is_ok() {
+            bail!(
+                "NotionAPI can't be used inside a tokio runtime, use NotionClient from async code"
+            );
+        }
+
+        return self.runtime.block_on(operation);
+    }
+}
+
+impl traits::NotionCaller for NotionAPI {
+    fn list_tasks(&self, database_id: &str, status: &Option<TaskStatus>) -> Result<Vec<Task>> {
+        return self.run(self.client.list_tasks(database_id, status));
+    }
+
+    fn list_tasks_edited_since(
+        &self,
+        database_id: &str,
+        since: &DateTime<Utc>,
+    ) -> Result<EditedTasks> {
+        return self.run(self.client.list_tasks_edited_since(database_id, since));
+    }
+
+    fn get_task(&self, id: &str) -> Result<Task> {
+        return self.run(self.client.get_task(id));
+    }
+
+    fn get_task_body(&self, id: &str) -> Result<Vec<Value>> {
+        return self.run(self.client.get_task_body(id));
+    }
+
+    fn get_database_schema(&self, database_id: &str) -> Result<Schema> {
+        return self.run(self.client.get_database_schema(database_id));
+    }
+
+    fn get_database_title(&self, database_id: &str) -> Result<String> {
+        return self.run(self.client.get_database_title(database_id));
+    }
+
+    fn add_task(
+        &self,
+        database_id: &str,
+        title: &str,
+        status: &TaskStatus,
+        body: &[Value],
+        properties: &Map<String, Value>,
+    ) -> Result<()> {
+        return self.run(
+            self.client
+                .add_task(database_id, title, status, body, properties),
+        );
+    }
+
+    fn add_tasks(&self, database_id: &str, tasks: &[NewTask]) -> Result<Vec<Result<()>>> {
+        return self.run(async { Ok(self.client.add_tasks(database_id, tasks).await) });
+    }
+
+    fn list_eligible_databases(&self) -> Result<Vec<Database>> {
+        return self.run(self.client.list_eligible_databases());
+    }
+
+    fn create_database(
+        &self,
+        parent_page_id: &str,
+        title: &str,
+        mapping: &PropertyMapping,
+    ) -> Result<Option<Database>> {
+        return self.run(self.client.create_database(parent_page_id, title, mapping));
+    }
+
+    fn mark_as_done(&self, ids: &[String]) -> Result<()> {
+        return self.run(self.client.mark_as_done(ids));
+    }
+
+    fn update_task(&self, id: &str, to: &Option<TaskStatus>, name: &Option<String>) -> Result<()> {
+        return self.run(self.client.update_task(id, to, name));
+    }
+
+    fn update_tasks(
+        &self,
+        ids: &[String],
+        to: &Option<TaskStatus>,
+        name: &Option<String>,
+    ) -> Result<()> {
+        return self.run(self.client.update_tasks(ids, to, name));
+    }
+
+    fn archive_task(&self, id: &str) -> Result<()> {
+        return self.run(self.client.archive_task(id));
+    }
+
+    fn archive_tasks(&self, ids: &[String]) -> Result<()> {
+        return self.run(self.client.archive_tasks(ids));
+    }
+
+    fn restore_task(&self, id: &str) -> Result<()> {
+        return self.run(self.client.restore_task(id));
+    }
+
+    fn add_comment(&self, id: &str, text: &str) -> Result<()> {
+        return self.run(self.client.add_comment(id, text));
+    }
+
+    fn list_comments(&self, id: &str) -> Result<Vec<Comment>> {
+        return self.run(self.client.list_comments(id));
+    }
+
+    fn get_current_user(&self) -> Result<String> {
+        return self.run(self.client.get_current_user());
+    }
+}
+
+/// Body of a request to the endpoint /v1/databases/:id/query
+#[derive(Serialize, Debug)]
+struct DatabaseQueryRequest<'a> {
+    /// Only pages matching the filter are returned
+    #[serde(skip_serializing_if = "Option::is_none")]
+    filter: Option<&'a Filter>,
+    /// The order pages are returned in
+    #[serde(skip_serializing_if = "<[Sort]>::is_empty")]
+    sorts: &'a [Sort],
+    /// Cursor returned with the previous page of results
+    #[serde(skip_serializing_if = "Option::is_none")]
+    start_cursor: Option<String>,
+    /// How many pages to return at once, at most 100
+    page_size: u32,
+}
+
 /// Response from calling the endpoint /v1/databases/:id/query
 #[derive(Deserialize, Debug)]
 struct DatabaseQueryResponse {
     /// Either "list" or "error"
     object: String,
-    /// list of pages with the database
-    results: Vec<Page>,
+    /// list of pages with the database, parsed one by one so a malformed page doesn't fail the query
+    results: Vec<Value>,
+    /// Whether there are more pages to fetch
+    has_more: bool,
+    /// Cursor for the next page of results
+    next_cursor: Option<String>,
 }
 
-#[derive(Deserialize, Debug, Clone)]
-/// A notion page
-struct Page {
-    /// The ID of the page
-    id: String,
-    /// Page properties
-    /// Went with a weakly typed value here to avoid making a bunch of structs
-    properties: Value,
+/// Response from calling the endpoint /v1/blocks/:id/children
+#[derive(Deserialize, Debug)]
+struct BlockChildrenResponse {
+    /// Child blocks, in page order
+    results: Vec<Value>,
+    /// Whether there are more children to fetch
+    has_more: bool,
+    /// Cursor for the next page of children
+    next_cursor: Option<String>,
 }
 
+/// Response from calling the endpoint /v1/comments
 #[derive(Deserialize, Debug)]
-/// Notion API search response
-struct SearchResponse {
-    /// List of Databases
-    results: Vec<DatabaseSearchResponse>,
+struct CommentListResponse {
+    /// Comments, oldest first
+    results: Vec<CommentObject>,
+    /// Whether there are more comments to fetch
+    has_more: bool,
+    /// Cursor for the next page of comments
+    next_cursor: Option<String>,
 }
 
 #[derive(Deserialize, Debug)]
-/// Database object returned by search
-struct DatabaseSearchResponse {
-    /// Database ID
-    id: String,
-    /// Database title
-    title: Value,
-    /// Database properties
-    properties: Value,
+/// A notion comment
+struct CommentObject {
+    /// When the comment was created
+    created_time: String,
+    /// The user who created the comment, only the ID is populated
+    created_by: PartialUser,
+    /// Comment content
+    rich_text: Vec<RichText>,
 }
 
-impl DatabaseSearchResponse {
-    /// Check if the database is compatible with the app
-    pub fn has_required_statuses(&self) -> bool {
-        // Check for the three required statuses
-        let statuses = self.properties["Status"]["select"]["options"]
-            .as_array()
-            .expect("statuses not an array");
+#[derive(Deserialize, Debug)]
+/// Notion API search response
+struct SearchResponse {
+    /// List of Databases, parsed one by one so a malformed database doesn't fail the search
+    results: Vec<Value>,
+}
 
-        let mut status_set = HashSet::new();
+impl NotionClient {
+    /// Lists the tasks in the database
+    pub async fn list_tasks(
+        &self,
+        database_id: &str,
+        status: &Option<TaskStatus>,
+    ) -> Result<Vec<Task>> {
+        let filter = status.map(|s| self.mapping.status_filter(s));
 
-        for status in statuses {
-            let name = status["name"]
-                .as_str()
-                .expect("status does not have a name field");
+        let pages: Vec<Page> = self.get_pages_from_db(database_id, filter.as_ref()).await?;
 
-            status_set.insert(name);
-        }
+        return Ok(self.pages_to_tasks(&pages));
+    }
 
-        if status_set.contains("To Do")
-            && status_set.contains("Doing")
-            && status_set.contains("Done🙌")
-        {
-            return true;
+    /// Lists the tasks in the database edited on or after since, and the edited pages that are no longer tasks
+    /// Archived pages aren't returned, only listing every task shows which tasks are gone
+    pub async fn list_tasks_edited_since(
+        &self,
+        database_id: &str,
+        since: &DateTime<Utc>,
+    ) -> Result<EditedTasks> {
+        let filter = Filter::Timestamp(TimestampFilter::LastEditedTime {
+            last_edited_time: DateCondition::OnOrAfter(since.to_rfc3339()),
+        });
+
+        let pages: Vec<Page> = self.get_pages_from_db(database_id, Some(&filter)).await?;
+
+        let mut edited = EditedTasks::default();
+        for page in &pages {
+            match self.page_to_task(page) {
+                Ok(task) => edited.tasks.push(task),
+                Err(_) => edited.not_tasks.push(page.id.clone()),
+            }
         }
 
-        return false;
+        return Ok(edited);
     }
-}
 
-impl traits::NotionCaller for NotionAPI {
-    fn list_tasks(&self, database_id: &str, status: &Option<TaskStatus>) -> Result<Vec<Task>> {
-        let pages = self.get_pages_from_db(database_id, status)?;
+    /// Gets a single task by ID
+    pub async fn get_task(&self, id: &str) -> Result<Task> {
+        let url = self.base_url.join(&format!("/v1/pages/{}", id))?;
 
-        let mut tasks: Vec<Task> = Vec::with_capacity(pages.len());
+        let page: Page = self
+            .send(self.client.get(url))
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
 
-        // turn each page into a task by extracting specific information
-        // could refactor this to use the From<T> trait
-        for page in &pages {
-            let task_title = page.properties["Name"]["title"][0]["text"]["content"]
-                .as_str()
-                .expect("no title for page");
+        return self.page_to_task(&page);
+    }
 
-            let status: Result<TaskStatus> = page.try_into();
-            if status.is_err() {
-                continue;
-            }
-            tasks.push(Task::new(
-                page.id.clone(),
-                status.unwrap(),
-                task_title.to_string(),
-            ));
+    /// Gets the content blocks of a task, nested blocks are stored in their parent's children field
+    pub async fn get_task_body(&self, id: &str) -> Result<Vec<Value>> {
+        let mut blocks = self.get_block_children(id).await?;
+
+        // nested blocks are fetched separately and stored the same way they are sent on creation
+        // the blocks stay JSON since markdown is rendered from them as Notion sent them
+        let nested: Vec<(usize, String, String)> = blocks
+            .iter()
+            .enumerate()
+            .filter_map(|(i, block)| {
+                let Ok(Block {
+                    id: Some(block_id),
+                    has_children: true,
+                    ..
+                }) = serde_json::from_value::<Block>(block.clone())
+                else {
+                    return None;
+                };
+                let kind = block["type"].as_str()?;
+
+                Some((i, kind.to_string(), block_id))
+            })
+            .collect();
+
+        // the children of different blocks don't depend on each other, so they are fetched in parallel
+        let children: Vec<Vec<Value>> = stream::iter(&nested)
+            .map(|(_, _, block_id)| Box::pin(self.get_task_body(block_id)))
+            .buffered(MAX_CONCURRENT_REQUESTS)
+            .try_collect()
+            .await?;
+
+        for ((i, kind, _), children) in nested.iter().zip(children) {
+            blocks[*i][kind.as_str()]["children"] = Value::Array(children);
         }
 
-        return Ok(tasks);
+        return Ok(blocks);
     }
 
-    fn add_task(&self, database_id: &str, title: &str, status: &TaskStatus) -> Result<()> {
+    /// Gets the property schema of the database
+    pub async fn get_database_schema(&self, database_id: &str) -> Result<Schema> {
+        return Ok(self.get_database(database_id).await?.properties);
+    }
+
+    /// Gets the title of the database
+    pub async fn get_database_title(&self, database_id: &str) -> Result<String> {
+        return Ok(self.get_database(database_id).await?.plain_title());
+    }
+
+    /// Adds a task to the database, body holds the blocks that make up the page content
+    /// and properties holds values for properties other than the title and status
+    pub async fn add_task(
+        &self,
+        database_id: &str,
+        title: &str,
+        status: &TaskStatus,
+        body: &[Value],
+        properties: &Map<String, Value>,
+    ) -> Result<()> {
         let url = self.base_url.join("/v1/pages")?;
 
-        let status = status.as_notion_status();
+        // the title and status win over anything passed in properties
+        let mut page_properties = properties.clone();
+        page_properties.insert(self.mapping.title.clone(), title_value(title));
+        page_properties.insert(self.mapping.status.clone(), self.status_value(*status));
+
+        // Notion limits how many blocks a request carries and how deeply they nest,
+        // so the leading blocks that fit are sent with the page and the rest are appended to it
+        let sent = body
+            .iter()
+            .take(MAX_CHILDREN)
+            .take_while(|block| fits_in_request(block, 0))
+            .count();
 
         let payload: Value = json!(
         {
             "parent": {
                 "database_id":database_id
             },
-            "properties":{
-                "Name":{
-                    "title":[
-                        {
-                            "text": {
-                                "content":title
-                            }
-                        }
-                    ]
-                },
-                "Status":{
-                    "select":{
-                        "name":status
-                    }
-                }
-            }
+            "properties":page_properties,
+            "children":body[..sent]
         });
 
-        self.client
-            .post(url)
-            .bearer_auth(&self.token)
-            .json(&payload)
-            .send()?
-            .error_for_status()?;
+        let page = self.send_mutation(Method::POST, url, &payload).await?;
+        let page_id = page
+            .as_ref()
+            .and_then(|p| p["id"].as_str())
+            .unwrap_or(DRY_RUN_ID);
+
+        self.append_blocks(page_id, &body[sent..]).await?;
 
         return Ok(());
     }
 
-    fn list_eligible_databases(&self) -> Result<Vec<Database>> {
+    /// Adds several tasks to the database in parallel, returning whether each of them was added in the same order
+    /// A task that fails doesn't stop the others
+    pub async fn add_tasks(&self, database_id: &str, tasks: &[NewTask]) -> Vec<Result<()>> {
+        return stream::iter(tasks)
+            .map(|task| {
+                self.add_task(
+                    database_id,
+                    &task.title,
+                    &task.status,
+                    &task.body,
+                    &task.properties,
+                )
+            })
+            .buffered(MAX_CONCURRENT_REQUESTS)
+            .collect()
+            .await;
+    }
+
+    /// List all databases whose schema PropertyMapping::detect can map to tasks: a title property
+    /// and a select or status property with options that look like todo, doing and done
+    pub async fn list_eligible_databases(&self) -> Result<Vec<Database>> {
         let url = self.base_url.join("/v1/search")?;
 
         let payload: Value = json!({
@@ -186,24 +493,16 @@ impl traits::NotionCaller for NotionAPI {
             }
         });
 
-        let response = self
-            .client
-            .post(url)
-            .bearer_auth(&self.token)
-            .json(&payload)
-            .send()?;
+        let response = self.send(self.client.post(url).json(&payload)).await?;
 
         let mut databases: Vec<Database> = Vec::new();
 
-        let body: SearchResponse = response.json()?;
-
-        for db_result in body.results {
-            let title = db_result.title[0]["text"]["content"]
-                .as_str()
-                .expect("failed to get database title");
+        let body: SearchResponse = response.json().await?;
 
-            if db_result.has_required_statuses() {
-                let db = Database::new(db_result.id, title.to_string());
+        for db_result in parse_results::<DatabaseObject>(body.results, "database") {
+            // only databases with a recognisable title and status property can hold tasks
+            if let Some(mapping) = PropertyMapping::detect(&db_result.properties) {
+                let db = Database::new(db_result.id.clone(), db_result.plain_title(), mapping);
 
                 databases.push(db);
             }
@@ -212,147 +511,589 @@ impl traits::NotionCaller for NotionAPI {
         return Ok(databases);
     }
 
-    /// Finds first task that contains the given &str name
-    fn get_task_from_name(&self, database_id: &str, name: &str) -> Result<Task> {
-        let tasks = self.list_tasks(database_id, &None)?;
+    /// Create a task database under a page, with the title and status properties of the mapping
+    /// Returns None in dry run mode, where nothing is created
+    pub async fn create_database(
+        &self,
+        parent_page_id: &str,
+        title: &str,
+        mapping: &PropertyMapping,
+    ) -> Result<Option<Database>> {
+        let url = self.base_url.join("/v1/databases")?;
+
+        let payload = json!({
+            "parent":{
+                "type":"page_id",
+                "page_id":parent_page_id
+            },
+            "title":[markdown::plain_rich_text(title)],
+            "properties":{
+                mapping.title.as_str():{
+                    "title":{}
+                },
+                mapping.status.as_str():{
+                    "select":{
+                        "options":[
+                            { "name":mapping.todo, "color":"red" },
+                            { "name":mapping.doing, "color":"yellow" },
+                            { "name":mapping.done, "color":"green" }
+                        ]
+                    }
+                }
+            }
+        });
 
-        let matched = tasks
-            .iter()
-            .find(|task| task.title.to_lowercase().contains(&name.to_lowercase()));
+        let Some(body) = self.send_mutation(Method::POST, url, &payload).await? else {
+            return Ok(None);
+        };
+        let Some(id) = body["id"].as_str() else {
+            bail!("Notion didn't return the ID of the new database");
+        };
 
-        if let Some(task) = matched {
-            let owned: Task = task.to_owned();
-            return Ok(owned);
-        } else {
-            bail!("No matchingtasks found");
-        }
+        let mut mapping = mapping.clone();
+        mapping.status_type = "select".to_string();
+
+        return Ok(Some(Database::new(
+            id.to_string(),
+            title.to_string(),
+            mapping,
+        )));
     }
 
-    fn mark_as_done(&self, ids: &[String]) -> Result<()> {
-        for id in ids {
-            let url = self.base_url.join(&format!("/v1/pages/{}", id))?;
-
-            let payload = json!({
-                "properties":{
-                    "Status":{
-                        "select":{
-                            "name":"Done🙌"
-                        }
-                    }
-                }
-            });
+    /// Mark the given task ids as done
+    pub async fn mark_as_done(&self, ids: &[String]) -> Result<()> {
+        let payload = json!({
+            "properties":{
+                self.mapping.status.as_str():self.status_value(TaskStatus::Done)
+            }
+        });
 
-            self.client
-                .patch(url)
-                .bearer_auth(&self.token)
-                .json(&payload)
-                .send()?
-                .error_for_status()?;
-        }
+        // each task is its own request, so they are sent in parallel
+        let payload = &payload;
+        stream::iter(ids)
+            .map(|id| async move {
+                let url = self.base_url.join(&format!("/v1/pages/{}", id))?;
+                self.send_mutation(Method::PATCH, url, payload).await
+            })
+            .buffered(MAX_CONCURRENT_REQUESTS)
+            .try_collect::<Vec<_>>()
+            .await?;
 
         return Ok(());
     }
 
-    fn update_task(&self, id: &str, to: &Option<TaskStatus>, name: &Option<String>) -> Result<()> {
-        let mut payload = json!({
-            "properties":{}
+    /// Archive the task, removing it from the database
+    pub async fn archive_task(&self, id: &str) -> Result<()> {
+        return self.set_archived(id, true).await;
+    }
+
+    /// Archive the given task ids in parallel
+    pub async fn archive_tasks(&self, ids: &[String]) -> Result<()> {
+        stream::iter(ids)
+            .map(|id| self.set_archived(id, true))
+            .buffered(MAX_CONCURRENT_REQUESTS)
+            .try_collect::<Vec<_>>()
+            .await?;
+
+        return Ok(());
+    }
+
+    /// Restore an archived task
+    pub async fn restore_task(&self, id: &str) -> Result<()> {
+        return self.set_archived(id, false).await;
+    }
+
+    /// Add a comment to the task
+    pub async fn add_comment(&self, id: &str, text: &str) -> Result<()> {
+        let url = self.base_url.join("/v1/comments")?;
+
+        let payload = json!({
+            "parent":{
+                "page_id":id
+            },
+            "rich_text":[markdown::plain_rich_text(text)]
         });
 
-        let map = payload["properties"]
-            .as_object_mut()
-            .expect("properties field was not an object");
+        self.send_mutation(Method::POST, url, &payload).await?;
 
-        if let Some(status) = to {
-            let value = json!({
-                "select":{
-                    "name":status.as_notion_status()
-                }
-            });
+        return Ok(());
+    }
+
+    /// List the comments on a task, oldest first
+    pub async fn list_comments(&self, id: &str) -> Result<Vec<Comment>> {
+        let mut objects = Vec::new();
+        let mut cursor: Option<String> = None;
+
+        loop {
+            let mut url = self.base_url.join("/v1/comments")?;
+            url.query_pairs_mut()
+                .append_pair("block_id", id)
+                .append_pair("page_size", "100");
+            if let Some(c) = &cursor {
+                url.query_pairs_mut().append_pair("start_cursor", c);
+            }
+
+            let body: CommentListResponse = self
+                .send(self.client.get(url))
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+
+            objects.extend(body.results);
+
+            match body.next_cursor {
+                Some(next) if body.has_more => cursor = Some(next),
+                _ => break,
+            }
+        }
 
-            map.insert("Status".to_string(), value);
+        // comments only reference their author, so look each one up once, in parallel
+        let mut user_ids: Vec<&str> = objects.iter().map(|o| o.created_by.id.as_str()).collect();
+        user_ids.sort_unstable();
+        user_ids.dedup();
+
+        let authors: HashMap<&str, String> = stream::iter(user_ids)
+            .map(|user_id| async move {
+                let name = self.get_user_name(user_id).await?;
+                return Ok::<_, anyhow::Error>((user_id, name));
+            })
+            .buffered(MAX_CONCURRENT_REQUESTS)
+            .try_collect()
+            .await?;
+
+        let mut comments = Vec::with_capacity(objects.len());
+
+        for object in &objects {
+            let text = rich_text::plain_text(&object.rich_text);
+
+            comments.push(Comment::new(
+                authors[object.created_by.id.as_str()].clone(),
+                object.created_time.clone(),
+                text,
+            ));
+        }
+
+        return Ok(comments);
+    }
+
+    /// Get the name of the integration the token belongs to, fails if the token isn't valid
+    pub async fn get_current_user(&self) -> Result<String> {
+        let url = self.base_url.join("/v1/users/me")?;
+
+        let user: User = self
+            .send(self.client.get(url))
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        return Ok(user
+            .name
+            .unwrap_or_else(|| "unnamed integration".to_string()));
+    }
+
+    /// Update the task to the supplied status and title
+    /// At least one of the supplied Optional values will be supplied
+    pub async fn update_task(
+        &self,
+        id: &str,
+        to: &Option<TaskStatus>,
+        name: &Option<String>,
+    ) -> Result<()> {
+        let mut properties = Map::new();
+
+        if let Some(status) = to {
+            properties.insert(self.mapping.status.clone(), self.status_value(*status));
         }
 
         if let Some(name) = name {
-            let value = json!({
-                "title":[
-                        {
-                            "text": {
-                                "content":name
-                            }
-                        }
-                    ]
-            });
-
-            map.insert("Name".to_string(), value);
+            properties.insert(self.mapping.title.clone(), title_value(name));
         }
 
-        payload["properties"] = Value::Object(map.clone());
+        let payload = json!({ "properties": properties });
 
         let url = self.base_url.join(&format!("/v1/pages/{}", id))?;
 
-        self.client
-            .patch(url)
-            .bearer_auth(&self.token)
-            .json(&payload)
-            .send()?
-            .error_for_status()?;
+        self.send_mutation(Method::PATCH, url, &payload).await?;
+
+        return Ok(());
+    }
+
+    /// Update the given task ids to the supplied status and title in parallel
+    pub async fn update_tasks(
+        &self,
+        ids: &[String],
+        to: &Option<TaskStatus>,
+        name: &Option<String>,
+    ) -> Result<()> {
+        stream::iter(ids)
+            .map(|id| self.update_task(id, to, name))
+            .buffered(MAX_CONCURRENT_REQUESTS)
+            .try_collect::<Vec<_>>()
+            .await?;
 
         return Ok(());
     }
 }
 
-impl NotionAPI {
-    /// hit the notion API and return a list of pages found in the db
-    /// optionally filters the on TaskStatus
-    fn get_pages_from_db(
+impl NotionClient {
+    /// hit the notion API and return the database object
+    async fn get_database(&self, database_id: &str) -> Result<DatabaseObject> {
+        let url = self
+            .base_url
+            .join(&format!("/v1/databases/{}", database_id))?;
+
+        let database: DatabaseObject = self
+            .send(self.client.get(url))
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        return Ok(database);
+    }
+
+    /// hit the notion API and return every page found in the db, following pagination
+    /// optionally filtered with a notion filter object, pages are parsed as T
+    async fn get_pages_from_db<T: DeserializeOwned>(
         &self,
         database_id: &str,
-        status: &Option<TaskStatus>,
-    ) -> Result<Vec<Page>> {
+        filter: Option<&Filter>,
+    ) -> Result<Vec<T>> {
         let url = self
             .base_url
-            .join(&format!("/v1/databases/{}/query", &database_id))?;
+            .join(&format!("/v1/databases/{}/query", database_id))?;
+
+        let mut pages = Vec::new();
+        let mut cursor: Option<String> = None;
+
+        loop {
+            let payload = DatabaseQueryRequest {
+                filter,
+                sorts: &[],
+                start_cursor: cursor.take(),
+                page_size: 100,
+            };
+
+            let response = self
+                .send(self.client.post(url.clone()).json(&payload))
+                .await?
+                .error_for_status()?;
 
-        let mut request = self.client.post(url).bearer_auth(&self.token);
+            let body: DatabaseQueryResponse = response.json().await?;
 
-        if let Some(s) = status {
-            let filter = s.as_notion_status();
-            let payload = Some(json!({
-                "filter":{
-                    "property":"Status",
-                    "select":{
-                        "equals":filter
-                    }
+            if body.object != "list" {
+                bail!("Response was not a list of pages")
+            }
+
+            pages.extend(parse_results::<T>(body.results, "page"));
+
+            match body.next_cursor {
+                Some(next) if body.has_more => cursor = Some(next),
+                _ => break,
+            }
+        }
+
+        return Ok(pages);
+    }
+
+    /// send a request with the token once fewer than MAX_CONCURRENT_REQUESTS are in flight
+    /// rate limited requests are retried after the delay Notion asks for in Retry-After
+    async fn send(&self, request: RequestBuilder) -> Result<Response> {
+        let request = request.bearer_auth(&self.token);
+        let mut retries = 0;
+
+        loop {
+            let Some(attempt) = request.try_clone() else {
+                bail!("the request can't be retried");
+            };
+
+            // the permit is released before waiting, so a rate limited request doesn't hold up others
+            let response = {
+                let _permit = self.limiter.acquire().await?;
+                attempt.send().await?
+            };
+            if response.status() != StatusCode::TOO_MANY_REQUESTS || retries == MAX_RETRIES {
+                return Ok(response);
+            }
+
+            let delay = response
+                .headers()
+                .get(RETRY_AFTER)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.trim().parse::<u64>().ok())
+                .map_or(DEFAULT_RETRY_AFTER, Duration::from_secs);
+            tokio::time::sleep(delay).await;
+            retries += 1;
+        }
+    }
+
+    /// send a request that modifies the workspace
+    /// in dry run mode the method, url and payload are printed and nothing is sent
+    /// returns the response body, or None in dry run mode
+    async fn send_mutation(
+        &self,
+        method: Method,
+        url: Url,
+        payload: &Value,
+    ) -> Result<Option<Value>> {
+        if self.dry_run {
+            println!("{} {}", method, url);
+            println!("{}", serde_json::to_string_pretty(payload)?);
+            return Ok(None);
+        }
+
+        let body = self
+            .send(self.client.request(method, url).json(payload))
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        return Ok(Some(body));
+    }
+
+    /// append blocks to a page or block, split into requests Notion accepts
+    /// blocks whose children don't fit in a request are sent without them, and their children are appended once they exist
+    async fn append_blocks(&self, parent_id: &str, blocks: &[Value]) -> Result<()> {
+        let url = self
+            .base_url
+            .join(&format!("/v1/blocks/{}/children", parent_id))?;
+
+        for chunk in blocks.chunks(MAX_CHILDREN) {
+            let mut deferred: Vec<(usize, Value)> = Vec::new();
+            let mut children = chunk.to_vec();
+            for (i, block) in children.iter_mut().enumerate() {
+                if fits_in_request(block, 0) {
+                    continue;
                 }
-            }));
+                let kind = block["type"].as_str().unwrap_or_default().to_string();
+                if let Some(nested) = block[kind.as_str()]
+                    .as_object_mut()
+                    .and_then(|b| b.remove("children"))
+                {
+                    deferred.push((i, nested));
+                }
+            }
 
-            request = request.json(&payload);
+            let payload = json!({ "children": children });
+            let response = self
+                .send_mutation(Method::PATCH, url.clone(), &payload)
+                .await?;
+
+            for (i, nested) in deferred {
+                let block_id = response
+                    .as_ref()
+                    .and_then(|r| r["results"][i]["id"].as_str())
+                    .unwrap_or(DRY_RUN_ID);
+                let nested = nested.as_array().map_or(&[] as &[Value], |v| v);
+
+                Box::pin(self.append_blocks(block_id, nested)).await?;
+            }
         }
 
-        let response = request.send()?.error_for_status()?;
+        return Ok(());
+    }
+
+    /// hit the notion API to archive or restore a page
+    async fn set_archived(&self, id: &str, archived: bool) -> Result<()> {
+        let url = self.base_url.join(&format!("/v1/pages/{}", id))?;
+
+        let payload = json!({ "archived": archived });
+
+        self.send_mutation(Method::PATCH, url, &payload).await?;
+
+        return Ok(());
+    }
+
+    /// The value of the status property for a task status
+    fn status_value(&self, status: TaskStatus) -> Value {
+        return json!({
+            self.mapping.status_type.as_str():{
+                "name":self.mapping.option_name(status)
+            }
+        });
+    }
+
+    /// turn each page into a task by extracting specific information, pages that aren't tasks are skipped
+    fn pages_to_tasks(&self, pages: &[Page]) -> Vec<Task> {
+        return pages
+            .iter()
+            .filter_map(|page| self.page_to_task(page).ok())
+            .collect();
+    }
+
+    /// turn a page into a task, fails if its status isn't one of the mapped options
+    fn page_to_task(&self, page: &Page) -> Result<Task> {
+        let title = match page.properties.get(&self.mapping.title) {
+            Some(PropertyValue::Title { title }) => rich_text::plain_text(title),
+            _ => bail!("no {} title property in page", self.mapping.title),
+        };
+
+        let option = match page.properties.get(&self.mapping.status) {
+            Some(PropertyValue::Select { select: option })
+            | Some(PropertyValue::Status { status: option }) => option,
+            _ => bail!("no {} property in page", self.mapping.status),
+        };
+        let status = match option.as_ref().map(|o| self.mapping.status_of(&o.name)) {
+            Some(Some(status)) => status,
+            Some(None) => bail!("unknown status"),
+            None => bail!("status not set on page"),
+        };
+
+        // the title and status are already fields of the task
+        let properties = page
+            .properties
+            .iter()
+            .filter(|(name, _)| **name != self.mapping.title && **name != self.mapping.status)
+            .map(|(name, property)| (name.clone(), property.plain_text()))
+            .collect();
+
+        return Ok(Task::new(
+            page.id.clone(),
+            status,
+            title,
+            page.last_edited_time,
+            page.archived,
+            properties,
+        ));
+    }
 
-        let body: DatabaseQueryResponse = response.json()?;
+    /// hit the notion API and return the name of a user
+    /// falls back to the user ID if the integration isn't allowed to read user information
+    async fn get_user_name(&self, id: &str) -> Result<String> {
+        let url = self.base_url.join(&format!("/v1/users/{}", id))?;
 
-        if body.object != "list" {
-            bail!("Response was not a list of pages")
+        let response = self.send(self.client.get(url)).await?;
+        if !response.status().is_success() {
+            return Ok(id.to_string());
         }
 
-        return Ok(body.results);
+        let user: User = response.json().await?;
+
+        return Ok(user.name.unwrap_or_else(|| id.to_string()));
+    }
+
+    /// hit the notion API and return every child block of a page or block
+    async fn get_block_children(&self, id: &str) -> Result<Vec<Value>> {
+        let mut blocks = Vec::new();
+        let mut cursor: Option<String> = None;
+
+        loop {
+            let mut url = self.base_url.join(&format!("/v1/blocks/{}/children", id))?;
+            url.query_pairs_mut().append_pair("page_size", "100");
+            if let Some(c) = &cursor {
+                url.query_pairs_mut().append_pair("start_cursor", c);
+            }
+
+            let body: BlockChildrenResponse = self
+                .send(self.client.get(url))
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+
+            blocks.extend(body.results);
+
+            match body.next_cursor {
+                Some(next) if body.has_more => cursor = Some(next),
+                _ => break,
+            }
+        }
+
+        return Ok(blocks);
     }
 }
 
-impl TryFrom<&Page> for TaskStatus {
-    type Error = anyhow::Error;
+/// Parse the objects of a list response one by one, objects that can't be read are reported and skipped
+fn parse_results<T: DeserializeOwned>(results: Vec<Value>, kind: &str) -> Vec<T> {
+    let mut parsed = Vec::with_capacity(results.len());
+
+    for result in results {
+        match serde_json::from_value::<T>(result.clone()) {
+            Ok(object) => parsed.push(object),
+            Err(e) => {
+                e_yellow_ln!(
+                    "Skipped {} {}, it couldn't be read: {}",
+                    kind,
+                    result["id"].as_str().unwrap_or("without an ID"),
+                    e
+                );
+            }
+        }
+    }
 
-    fn try_from(page: &Page) -> Result<Self, Self::Error> {
-        let status_text = page.properties["Status"]["select"]["name"].as_str();
+    return parsed;
+}
+
+/// Whether a block and its children can be sent in one request, when the block sits depth levels below the request's blocks
+fn fits_in_request(block: &Value, depth: usize) -> bool {
+    let kind = block["type"].as_str().unwrap_or_default();
+    let Some(children) = block[kind]["children"].as_array() else {
+        return true;
+    };
+    if children.is_empty() {
+        return true;
+    }
 
-        return match status_text {
-            Some("To Do") => Ok(TaskStatus::Todo),
-            Some("Doing") => Ok(TaskStatus::Doing),
-            Some("Done🙌") => Ok(TaskStatus::Done),
-            None => bail!("status text not found in page"),
-            _ => bail!("unknown status"),
+    return depth < MAX_NESTING
+        && children.len() <= MAX_CHILDREN
+        && children
+            .iter()
+            .all(|child| fits_in_request(child, depth + 1));
+}
+
+/// The value of a title property holding plain text
+fn title_value(title: &str) -> Value {
+    return json!({
+        "title":[
+            {
+                "text": {
+                    "content":title
+                }
+            }
+        ]
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A bulleted list item nested depth levels deep, with the given number of leaf items at the bottom
+    fn nested_item(depth: usize, leaves: usize) -> Value {
+        let children: Vec<Value> = if depth == 0 {
+            (0..leaves).map(|_| nested_item_leaf()).collect()
+        } else {
+            vec![nested_item(depth - 1, leaves)]
         };
+
+        return json!({
+            "type": "bulleted_list_item",
+            "bulleted_list_item": { "rich_text": [], "children": children }
+        });
+    }
+
+    /// A bulleted list item without children
+    fn nested_item_leaf() -> Value {
+        return json!({
+            "type": "bulleted_list_item",
+            "bulleted_list_item": { "rich_text": [] }
+        });
+    }
+
+    #[test]
+    fn blocks_within_limits_fit() {
+        assert!(fits_in_request(&nested_item_leaf(), 0));
+        assert!(fits_in_request(&nested_item(1, 3), 0));
+        assert!(fits_in_request(&nested_item(0, MAX_CHILDREN), 0));
+    }
+
+    #[test]
+    fn deep_or_wide_blocks_do_not_fit() {
+        assert!(!fits_in_request(&nested_item(2, 1), 0));
+        assert!(!fits_in_request(&nested_item(0, MAX_CHILDREN + 1), 0));
+        assert!(!fits_in_request(&nested_item(1, 1), 1));
     }
 }
@@ -0,0 +1,195 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+use chrono::{Duration, Utc};
+
+use crate::{models::cache::DatabaseCache, traits::NotionCaller};
+
+/// What a pull changed in the cache
+pub struct PullSummary {
+    /// Whether every task was fetched
+    pub full: bool,
+    /// Tasks added or updated
+    pub updated: usize,
+    /// Tasks dropped because they were archived, deleted or are no longer tasks
+    pub removed: usize,
+}
+
+/// How long a cache is pulled incrementally before every task is fetched again
+/// Incremental pulls can't see deleted or archived pages, the full pull drops them from the cache
+pub const FULL_PULL_INTERVAL_HOURS: i64 = 24;
+
+/// Bring a cached database up to date
+/// Only tasks edited since the sync cursor are fetched, unless full is set, the database was never synced
+/// or its last full pull is older than FULL_PULL_INTERVAL_HOURS
+pub fn pull(
+    notion: &dyn NotionCaller,
+    database_id: &str,
+    db: &mut DatabaseCache,
+    full: bool,
+) -> Result<PullSummary> {
+    // notion rounds last_edited_time down to the minute, so step back to not miss edits made during this pull
+    let next_cursor = Utc::now() - Duration::minutes(1);
+
+    let stale = db
+        .refreshed_at
+        .is_none_or(|at| Utc::now() - at > Duration::hours(FULL_PULL_INTERVAL_HOURS));
+
+    let summary = match db.sync_cursor {
+        Some(cursor) if !full && !stale => {
+            let edited = notion.list_tasks_edited_since(database_id, &cursor)?;
+
+            // pages edited into something that isn't a task, e.g. by clearing the status, are dropped
+            // like a full pull would drop them
+            let (updated, archived) = db.merge(edited.tasks);
+            let removed = archived + db.remove(&edited.not_tasks);
+
+            PullSummary {
+                full: false,
+                updated,
+                removed,
+            }
+        }
+        _ => {
+            let tasks = notion.list_tasks(database_id, &None)?;
+            let listed: HashSet<String> = tasks.iter().map(|t| t.id.clone()).collect();
+            let removed = db.retain_listed(&listed);
+            db.tasks.clear();
+            let (updated, _) = db.merge(tasks);
+            db.refreshed_at = Some(Utc::now());
+
+            PullSummary {
+                full: true,
+                updated,
+                removed,
+            }
+        }
+    };
+
+    db.sync_cursor = Some(next_cursor);
+
+    return Ok(summary);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        models::task_status::TaskStatus,
+        services::fake::{task, time, FakeNotion},
+    };
+
+    /// A cache pulled in full a minute ago
+    fn pulled(notion: &FakeNotion) -> DatabaseCache {
+        let mut db = DatabaseCache::default();
+        pull(notion, "db", &mut db, false).unwrap();
+
+        return db;
+    }
+
+    #[test]
+    fn first_pull_fetches_everything() {
+        let notion = FakeNotion::with_tasks(vec![
+            task("1", "One", TaskStatus::Todo, 0),
+            task("2", "Two", TaskStatus::Done, 0),
+        ]);
+        let before = Utc::now();
+
+        let mut db = DatabaseCache::default();
+        let summary = pull(&notion, "db", &mut db, false).unwrap();
+
+        assert!(summary.full);
+        assert_eq!((summary.updated, summary.removed), (2, 0));
+        assert_eq!(db.tasks.len(), 2);
+        assert!(db.refreshed_at.unwrap() >= before);
+    }
+
+    #[test]
+    fn cursor_steps_back_a_minute() {
+        let notion = FakeNotion::with_tasks(vec![task("1", "One", TaskStatus::Todo, 0)]);
+        let before = Utc::now();
+
+        let db = pulled(&notion);
+
+        let cursor = db.sync_cursor.unwrap();
+        assert!(cursor >= before - Duration::minutes(1));
+        assert!(cursor <= Utc::now() - Duration::minutes(1));
+    }
+
+    #[test]
+    fn incremental_pull_only_merges_edited_tasks() {
+        let notion = FakeNotion::with_tasks(vec![
+            task("1", "One", TaskStatus::Todo, 0),
+            task("2", "Two", TaskStatus::Todo, 0),
+        ]);
+        let mut db = pulled(&notion);
+
+        // a task edited in Notion since the pull, and one only changed in the cache
+        notion
+            .update_task("1", &Some(TaskStatus::Doing), &None)
+            .unwrap();
+        db.tasks.get_mut("2").unwrap().title = "Local".to_string();
+
+        let summary = pull(&notion, "db", &mut db, false).unwrap();
+
+        assert!(!summary.full);
+        assert_eq!((summary.updated, summary.removed), (1, 0));
+        assert_eq!(db.tasks["1"].status, TaskStatus::Doing);
+        assert_eq!(db.tasks["2"].title, "Local");
+    }
+
+    #[test]
+    fn incremental_pull_drops_pages_that_are_no_longer_tasks() {
+        let notion = FakeNotion::with_tasks(vec![
+            task("1", "One", TaskStatus::Todo, 0),
+            task("2", "Two", TaskStatus::Todo, 0),
+        ]);
+        let mut db = pulled(&notion);
+
+        // the status of 2 was cleared, so it no longer reads as a task
+        let mut state = notion.0.borrow_mut();
+        state.tasks.remove("2");
+        state.not_tasks.push("2".to_string());
+        drop(state);
+
+        let summary = pull(&notion, "db", &mut db, false).unwrap();
+
+        assert!(!summary.full);
+        assert_eq!(summary.removed, 1);
+        assert_eq!(db.tasks.keys().collect::<Vec<_>>(), vec!["1"]);
+    }
+
+    #[test]
+    fn incremental_pull_keeps_archived_tasks_until_a_full_pull() {
+        let notion = FakeNotion::with_tasks(vec![
+            task("1", "One", TaskStatus::Todo, 0),
+            task("2", "Two", TaskStatus::Todo, 0),
+        ]);
+        let mut db = pulled(&notion);
+        notion.0.borrow_mut().tasks.remove("2");
+
+        let summary = pull(&notion, "db", &mut db, false).unwrap();
+        assert_eq!(summary.removed, 0);
+        assert_eq!(db.tasks.len(), 2);
+
+        let summary = pull(&notion, "db", &mut db, true).unwrap();
+        assert!(summary.full);
+        assert_eq!((summary.updated, summary.removed), (1, 1));
+        assert_eq!(db.tasks.keys().collect::<Vec<_>>(), vec!["1"]);
+    }
+
+    #[test]
+    fn stale_caches_are_pulled_in_full() {
+        let notion = FakeNotion::with_tasks(vec![task("1", "One", TaskStatus::Todo, 0)]);
+        let mut db = pulled(&notion);
+        notion.0.borrow_mut().tasks.clear();
+        db.refreshed_at = Some(time(0));
+
+        let summary = pull(&notion, "db", &mut db, false).unwrap();
+
+        assert!(summary.full);
+        assert_eq!(summary.removed, 1);
+        assert!(db.tasks.is_empty());
+        assert!(db.refreshed_at.unwrap() > time(0));
+    }
+}
@@ -1,30 +1,110 @@
+use std::path::{Path, PathBuf};
+
 use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use serde_json::{Map, Value};
 
-use crate::models::{config::AppConfig, database::Database, task::Task, task_status::TaskStatus};
+use crate::models::{
+    api::schema::Schema,
+    cache::Cache,
+    comment::Comment,
+    config::{AppConfig, DatabaseConfig},
+    database::Database,
+    journal::Journal,
+    list_output::ListOutput,
+    listing::Listing,
+    property_mapping::PropertyMapping,
+    task::{EditedTasks, NewTask, Task},
+    task_format::TaskFormat,
+    task_selector::TaskSelector,
+    task_status::TaskStatus,
+};
 
 /// Defines the operations that can be performed on a task
 pub trait TaskHandler {
-    /// Adds a task to the database
-    fn add(&self, database_id: &str, name: &str, status: &TaskStatus) -> Result<()>;
-    /// Lists the tasks in the database with the specified status
-    fn list(&self, database_id: &str, status: &Option<TaskStatus>, with_id: &bool) -> Result<()>;
-    /// Marks a list of tasks as done
-    fn done(&self, database_id: &str, ids: &[String], name: Option<&str>) -> Result<()>;
-    /// Modifies the TaskStatus of multiple tasks
-    fn update(&self, ids: &str, to: &Option<TaskStatus>, name: &Option<String>) -> Result<()>;
+    /// Adds a task to the database, with an optional markdown body
+    /// A name of "-" adds a task for every line of stdin
+    fn add(
+        &self,
+        database: &DatabaseConfig,
+        name: &str,
+        status: &TaskStatus,
+        body: &Option<String>,
+    ) -> Result<()>;
+    /// Lists the tasks in the databases with the specified status
+    /// Tasks of several databases are merged and shown with the database they came from
+    fn list(
+        &self,
+        databases: &[DatabaseConfig],
+        status: &Option<TaskStatus>,
+        with_id: &bool,
+        output: ListOutput,
+    ) -> Result<()>;
+    /// Marks the selected tasks as done
+    fn done(&self, database: &DatabaseConfig, selector: &TaskSelector) -> Result<()>;
+    /// Modifies the TaskStatus and title of the selected tasks, a title can only be given to a single task
+    fn update(
+        &self,
+        database: &DatabaseConfig,
+        selector: &TaskSelector,
+        to: &Option<TaskStatus>,
+        title: &Option<String>,
+    ) -> Result<()>;
+    /// Prints the selected tasks along with their bodies as markdown
+    fn show(&self, database: &DatabaseConfig, selector: &TaskSelector) -> Result<()>;
+    /// Archives the selected tasks, optionally only the ones that haven't been edited for older_than
+    /// Asks for confirmation before archiving more than one task unless yes is set
+    fn archive(
+        &self,
+        database: &DatabaseConfig,
+        selector: &TaskSelector,
+        older_than: &Option<Duration>,
+        yes: bool,
+    ) -> Result<()>;
+    /// Restores an archived task
+    fn restore(&self, database: &DatabaseConfig, id: &str) -> Result<()>;
+    /// Adds a comment to a task
+    fn comment(&self, database: &DatabaseConfig, id: &str, text: &str) -> Result<()>;
+    /// Prints the comments on a task
+    fn comments(&self, database: &DatabaseConfig, id: &str) -> Result<()>;
+    /// Creates a task for every row of a file, rows matching an existing task by title or id column are skipped
+    fn import(
+        &self,
+        database: &DatabaseConfig,
+        file: &Path,
+        format: &Option<TaskFormat>,
+        id_column: &str,
+    ) -> Result<()>;
+    /// Writes every task with all its properties to a file, or stdout if no file is given
+    fn export(
+        &self,
+        database: &DatabaseConfig,
+        format: TaskFormat,
+        out: &Option<PathBuf>,
+    ) -> Result<()>;
 }
 
 /// Defines the config operations
 pub trait ConfigCommandHandler {
-    /// Saves the database_id for use in future calls
-    fn set_database(&self, database_id: &str) -> Result<()>;
+    /// Saves the database_id for use in future calls, with an optional alias to pick it with --database
+    fn set_database(&self, database_id: &str, alias: &Option<String>) -> Result<()>;
+
+    /// Adds another database task commands can use with --database, the first one added becomes the default
+    fn add_database(&self, database_id: &str, alias: &Option<String>) -> Result<()>;
 
-    /// Gets the persisted database_id
+    /// Prints the aliases of the databases in the config
+    fn list_aliases(&self) -> Result<()>;
+
+    /// Gets the persisted database_id and the other databases that were added
     fn get_database_id(&self) -> Result<()>;
 
     /// Prints titles and names of databases that can be used by the app
     fn list_databases(&self) -> Result<()>;
 
+    /// Creates a task database under a page and saves it for use in future calls
+    fn create_database(&self, parent_page_id: &str, title: &str) -> Result<()>;
+
+    /// Saves the integration token for use in future calls
     fn set_token(&self, token: &str) -> Result<()>;
 }
 
@@ -33,24 +113,144 @@ pub trait NotionCaller {
     /// Lists the tasks in the database
     fn list_tasks(&self, database_id: &str, status: &Option<TaskStatus>) -> Result<Vec<Task>>;
 
-    /// Adds a task to the database
-    fn add_task(&self, database_id: &str, title: &str, status: &TaskStatus) -> Result<()>;
+    /// Lists the tasks in the database edited on or after since, and the edited pages that are no longer tasks
+    /// Archived pages aren't returned, only listing every task shows which tasks are gone
+    fn list_tasks_edited_since(
+        &self,
+        database_id: &str,
+        since: &DateTime<Utc>,
+    ) -> Result<EditedTasks>;
+
+    /// Gets a single task by ID
+    fn get_task(&self, id: &str) -> Result<Task>;
+
+    /// Gets the content blocks of a task, nested blocks are stored in their parent's children field
+    fn get_task_body(&self, id: &str) -> Result<Vec<Value>>;
+
+    /// Gets the property schema of the database
+    fn get_database_schema(&self, database_id: &str) -> Result<Schema>;
+
+    /// Gets the title of the database
+    fn get_database_title(&self, database_id: &str) -> Result<String>;
 
-    /// List all databases that have these three statuses: To Do, Doing, and Done
+    /// Adds a task to the database, body holds the blocks that make up the page content
+    /// and properties holds values for properties other than the title and status
+    fn add_task(
+        &self,
+        database_id: &str,
+        title: &str,
+        status: &TaskStatus,
+        body: &[Value],
+        properties: &Map<String, Value>,
+    ) -> Result<()>;
+
+    /// Adds several tasks to the database at once, returning whether each of them was added in the same order
+    fn add_tasks(&self, database_id: &str, tasks: &[NewTask]) -> Result<Vec<Result<()>>>;
+
+    /// List all databases whose schema PropertyMapping::detect can map to tasks: a title property
+    /// and a select or status property with options that look like todo, doing and done
     fn list_eligible_databases(&self) -> Result<Vec<Database>>;
 
+    /// Create a task database under a page, with the title and status properties of the mapping
+    /// Returns None in dry run mode, where nothing is created
+    fn create_database(
+        &self,
+        parent_page_id: &str,
+        title: &str,
+        mapping: &PropertyMapping,
+    ) -> Result<Option<Database>>;
+
     /// Mark the given task ids as done
     fn mark_as_done(&self, ids: &[String]) -> Result<()>;
 
-    /// Return the first task that contains pattern
-    fn get_task_from_name(&self, database_id: &str, pattern: &str) -> Result<Task>;
-
     /// Update the task to the supplied status and title
     /// At least one of the supplied Optional values will be supplied
     fn update_task(&self, id: &str, to: &Option<TaskStatus>, name: &Option<String>) -> Result<()>;
+
+    /// Update the given task ids to the supplied status and title, see update_task
+    fn update_tasks(
+        &self,
+        ids: &[String],
+        to: &Option<TaskStatus>,
+        name: &Option<String>,
+    ) -> Result<()>;
+
+    /// Archive the task, removing it from the database
+    fn archive_task(&self, id: &str) -> Result<()>;
+
+    /// Archive the given task ids
+    fn archive_tasks(&self, ids: &[String]) -> Result<()>;
+
+    /// Restore an archived task
+    fn restore_task(&self, id: &str) -> Result<()>;
+
+    /// Add a comment to the task
+    fn add_comment(&self, id: &str, text: &str) -> Result<()>;
+
+    /// List the comments on a task, oldest first
+    fn list_comments(&self, id: &str) -> Result<Vec<Comment>>;
+
+    /// Get the name of the integration the token belongs to, fails if the token isn't valid
+    fn get_current_user(&self) -> Result<String>;
+
+    /// How many changes were queued for `notion sync` instead of sent, only clients with a journal queue changes
+    fn queued_changes(&self) -> usize {
+        return 0;
+    }
 }
 
+/// Loads and saves the app config
 pub trait ConfigService {
+    /// Get the config, the default config if none has been saved
     fn get_config(&self) -> Result<AppConfig>;
+    /// Replace the config
     fn set_config(&self, config: AppConfig) -> Result<()>;
+    /// Whether a config has been saved, get_config falls back to an empty config when it hasn't
+    fn exists(&self) -> Result<bool>;
+}
+
+/// Remembers the last task listing so tasks can be referred to by their index in it
+pub trait ListingService {
+    /// Get the last listing, empty if there hasn't been one
+    fn get_listing(&self) -> Result<Listing>;
+    /// Replace the last listing
+    fn set_listing(&self, listing: Listing) -> Result<()>;
+}
+
+/// Stores the local copy of databases
+pub trait CacheService {
+    /// Get the cache, empty if nothing has been cached yet
+    fn get_cache(&self) -> Result<Cache>;
+    /// Replace the cache
+    fn set_cache(&self, cache: &Cache) -> Result<()>;
+}
+
+/// Stores changes queued while Notion couldn't be reached
+pub trait JournalService {
+    /// Get the journal, empty if nothing is queued
+    fn get_journal(&self) -> Result<Journal>;
+    /// Replace the journal
+    fn set_journal(&self, journal: &Journal) -> Result<()>;
+}
+
+/// Defines the setup wizard
+pub trait InitCommandHandler {
+    /// Asks for a token and database, works out the database's property mapping and saves the config
+    fn init(&self) -> Result<()>;
+}
+
+/// Defines the configuration checks
+pub trait DoctorCommandHandler {
+    /// Checks the config, token, database and its schema, printing a line and a suggested fix for each problem
+    /// Fails if any check failed
+    fn doctor(&self) -> Result<()>;
+}
+
+/// Defines the sync operations
+pub trait SyncCommandHandler {
+    /// Replays queued changes against Notion and reports the ones that couldn't be applied,
+    /// then pulls the tasks of the given databases edited since the last sync into the cache, or every task if full is set
+    /// Each change is replayed in the database it was made in, whichever databases are pulled
+    /// Changes to pages edited in Notion since they were queued are conflicts and only applied if force is set
+    fn sync(&self, databases: &[DatabaseConfig], force: bool, full: bool) -> Result<()>;
 }